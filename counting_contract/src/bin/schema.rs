@@ -1,5 +1,5 @@
 use cosmwasm_schema::write_api;
-use counting_contract::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use counting_contract_0_1::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
 
 fn main() {
     write_api! {
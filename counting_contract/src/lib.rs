@@ -1,6 +1,6 @@
 use crate::msg::QueryMsg;
 use cosmwasm_std::{
-    to_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
 };
 
 #[cfg(not(feature = "library"))]
@@ -47,6 +47,6 @@ pub fn execute(
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     use msg::QueryMsg::*;
     match msg {
-        Value {} => to_binary(&contract::query::value(deps)?),
+        Value {} => to_json_binary(&contract::query::value(deps)?),
     }
 }
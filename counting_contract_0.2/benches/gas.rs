@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::ExitCode;
+
+use cosmwasm_std::{coin, coins, Addr, Binary, Empty};
+#[cfg(feature = "permits")]
+use counting_contract::msg::{OwnerPubkey, WithdrawPermit};
+use counting_contract::msg::{Attestation, ExecuteMsg, InstantiateMsg, QueryMsg, DEFAULT_COUNTER};
+use counting_contract::vm::{load_wasm, Call, VmApp};
+use counting_contract_interface::attestation;
+#[cfg(feature = "permits")]
+use counting_contract_interface::permit;
+#[cfg(feature = "permits")]
+use ed25519_zebra::{SigningKey as Ed25519SigningKey, VerificationKey as Ed25519VerificationKey};
+use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+use serde_json::json;
+
+//measures gas used by every entry point of the wasm build and checks it against gas-budget.toml
+
+const BUDGET_FILE: &str = "gas-budget.toml";
+
+struct Measured {
+    entry_point: &'static str,
+    gas_used: u64,
+}
+
+fn expect<T>(entry_point: &'static str, call: Call<T>) -> Result<Measured, String> {
+    call.result
+        .map_err(|err| format!("{entry_point} failed: {err}"))?;
+    Ok(Measured {
+        entry_point,
+        gas_used: call.gas_used,
+    })
+}
+
+fn measure() -> Result<Vec<Measured>, String> {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut app = VmApp::new();
+    let code_id = app.store_code(load_wasm(manifest_dir, "counting_contract")?)?;
+    let legacy_code_id = app.store_code(load_wasm(
+        &manifest_dir.join("../counting_contract"),
+        "counting_contract_0_1",
+    )?)?;
+
+    let owner = Addr::unchecked("owner");
+    let admin = Addr::unchecked("admin");
+    let sender = Addr::unchecked("sender");
+    app.init_balance(&sender, coins(100, "atom"));
+
+    let mut measured = vec![];
+    let msg = InstantiateMsg::new(coin(10, "atom"));
+    let (contract, call) = app.instantiate(code_id, &owner, &msg, &[], None);
+    measured.push(expect("instantiate", call)?);
+
+    let donate = ExecuteMsg::Donate { counter_id: None };
+    let call = app.execute(&contract, &sender, &donate, &coins(10, "atom"));
+    measured.push(expect("donate", call)?);
+    let call = app.execute(&contract, &sender, &donate, &coins(5, "atom"));
+    measured.push(expect("donate_below_minimum", call)?);
+
+    let call = app.query(&contract, &QueryMsg::Value { counter_id: None });
+    measured.push(expect("query_value", call)?);
+    let call = app.query(&contract, &QueryMsg::Config { counter_id: None });
+    measured.push(expect("query_config", call)?);
+    #[cfg(feature = "export")]
+    {
+        let call = app.query(&contract, &QueryMsg::ExportState { start_after: None, limit: None });
+        measured.push(expect("query_export_state", call)?);
+    }
+
+    let create_counter = ExecuteMsg::CreateCounter {
+        id: "named".to_owned(),
+        minimal_donation: coin(10, "atom"),
+        owner: None,
+    };
+    let call = app.execute(&contract, &sender, &create_counter, &[]);
+    measured.push(expect("create_counter", call)?);
+    let donate_named = ExecuteMsg::Donate {
+        counter_id: Some("named".to_owned()),
+    };
+    let call = app.execute(&contract, &sender, &donate_named, &coins(10, "atom"));
+    measured.push(expect("donate_named", call)?);
+    let counters = QueryMsg::Counters {
+        start_after: None,
+        limit: None,
+    };
+    let call = app.query(&contract, &counters);
+    measured.push(expect("query_counters", call)?);
+
+    let withdraw = ExecuteMsg::Withdraw { counter_id: None };
+    let call = app.execute(&contract, &owner, &withdraw, &[]);
+    measured.push(expect("withdraw", call)?);
+
+    //signatures are checked by the host, these cover what the contract does around them
+    let secp = SigningKey::from_slice(&[7; 32]).map_err(|err| err.to_string())?;
+    let set_attestor = ExecuteMsg::SetAttestor {
+        counter_id: None,
+        name: "kyc".to_owned(),
+        pubkey: Some(Binary::from(secp.verifying_key().to_encoded_point(true).as_bytes())),
+    };
+    let call = app.execute(&contract, &owner, &set_attestor, &[]);
+    expect("set_attestor", call)?;
+    let identity = "sender@example.com";
+    let hash = attestation::digest(contract.as_str(), DEFAULT_COUNTER, sender.as_str(), identity)
+        .map_err(|err| err.to_string())?;
+    let signature: Signature = secp.sign_prehash(&hash).map_err(|err| err.to_string())?;
+    let donate_attested = ExecuteMsg::DonateAttested {
+        counter_id: None,
+        attestation: Attestation {
+            attestor: "kyc".to_owned(),
+            identity: identity.to_owned(),
+            signature: Binary::from(&signature.to_bytes()[..]),
+        },
+    };
+    let call = app.execute(&contract, &sender, &donate_attested, &coins(10, "atom"));
+    measured.push(expect("donate_attested", call)?);
+
+    #[cfg(feature = "permits")]
+    {
+        let receiver = app.addr_make("receiver");
+        let expires = app.block().time.plus_seconds(60);
+        let permit = |nonce| WithdrawPermit {
+            counter_id: None,
+            receiver: receiver.to_string(),
+            amount: coins(5, "atom"),
+            nonce,
+            expires,
+        };
+        let set_pubkey = |pubkey| ExecuteMsg::SetOwnerPubkey { counter_id: None, pubkey: Some(pubkey) };
+
+        let pubkey = OwnerPubkey::Secp256k1(Binary::from(secp.verifying_key().to_encoded_point(true).as_bytes()));
+        let call = app.execute(&contract, &owner, &set_pubkey(pubkey), &[]);
+        expect("set_owner_pubkey", call)?;
+        let permit_secp = permit(0);
+        let hash = permit::digest(contract.as_str(), &permit_secp).map_err(|err| err.to_string())?;
+        let signature: Signature = secp.sign_prehash(&hash).map_err(|err| err.to_string())?;
+        let withdraw = ExecuteMsg::WithdrawWithPermit {
+            permit: permit_secp,
+            signature: Binary::from(&signature.to_bytes()[..]),
+        };
+        let call = app.execute(&contract, &sender, &withdraw, &[]);
+        measured.push(expect("withdraw_permit_secp256k1", call)?);
+
+        let ed25519 = Ed25519SigningKey::from([9; 32]);
+        let pubkey = OwnerPubkey::Ed25519(Binary::from(<[u8; 32]>::from(Ed25519VerificationKey::from(&ed25519))));
+        let call = app.execute(&contract, &owner, &set_pubkey(pubkey), &[]);
+        expect("set_owner_pubkey", call)?;
+        let permit_ed25519 = permit(1);
+        let hash = permit::digest(contract.as_str(), &permit_ed25519).map_err(|err| err.to_string())?;
+        let withdraw = ExecuteMsg::WithdrawWithPermit {
+            permit: permit_ed25519,
+            signature: Binary::from(<[u8; 64]>::from(ed25519.sign(hash.as_slice()))),
+        };
+        let call = app.execute(&contract, &sender, &withdraw, &[]);
+        measured.push(expect("withdraw_permit_ed25519", call)?);
+    }
+
+    //migration is measured on state written by the 0.1 release
+    let legacy_msg = json!({ "minimal_donation": { "denom": "atom", "amount": "10" } });
+    let (legacy, call) = app.instantiate(legacy_code_id, &owner, &legacy_msg, &[], Some(&admin));
+    expect("legacy instantiate", call)?;
+    let call = app.execute(&legacy, &sender, &donate, &coins(10, "atom"));
+    expect("legacy donate", call)?;
+    let call = app.migrate(&legacy, &admin, code_id, &Empty {});
+    measured.push(expect("migrate", call)?);
+
+    Ok(measured)
+}
+
+fn read_budget() -> Result<BTreeMap<String, u64>, String> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(BUDGET_FILE);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    toml::from_str(&content).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+fn run() -> Result<bool, String> {
+    let budget = read_budget()?;
+    let measured = measure()?;
+
+    let mut within_budget = true;
+    println!("{:<26} {:>14} {:>14}", "entry point", "gas used", "budget");
+    for Measured {
+        entry_point,
+        gas_used,
+    } in measured
+    {
+        let (limit, status) = match budget.get(entry_point) {
+            Some(&limit) if gas_used > limit => {
+                within_budget = false;
+                (limit.to_string(), "OVER BUDGET")
+            }
+            Some(&limit) => (limit.to_string(), ""),
+            None => ("-".to_owned(), ""),
+        };
+        let line = format!("{entry_point:<26} {gas_used:>14} {limit:>14} {status}");
+        println!("{}", line.trim_end());
+    }
+    Ok(within_budget)
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => {
+            eprintln!("error: gas budget exceeded, see {BUDGET_FILE}");
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,34 @@
+#!/usr/bin/env bash
+# lints and tests every combination of the optional subsystems, then reports
+# the size of the wasm built with each of them
+set -euo pipefail
+cd "$(dirname "$0")"
+
+# keep in sync with the subsystem features in Cargo.toml
+SUBSYSTEMS=(export matching permits rounds)
+
+combinations() {
+    local count=${#SUBSYSTEMS[@]}
+    for ((mask = 0; mask < 1 << count; mask++)); do
+        local combo=()
+        for ((idx = 0; idx < count; idx++)); do
+            if ((mask & 1 << idx)); then
+                combo+=("${SUBSYSTEMS[idx]}")
+            fi
+        done
+        (IFS=,; echo "${combo[*]}")
+    done
+}
+
+sizes=()
+while read -r combo; do
+    echo "== features: ${combo:-<none>}"
+    cargo clippy --all-targets --no-default-features --features "tests${combo:+,$combo}" -- -D warnings
+    cargo test --no-default-features --features "tests${combo:+,$combo}"
+    RUSTFLAGS="-C link-arg=-s" cargo wasm --no-default-features --features "$combo" --target-dir target/features
+    wasm=target/features/wasm32-unknown-unknown/release/counting_contract.wasm
+    sizes+=("$(printf '%10d  %s' "$(wc -c < "$wasm")" "${combo:-<none>}")")
+done < <(combinations)
+
+echo "== wasm size in bytes"
+printf '%s\n' "${sizes[@]}"
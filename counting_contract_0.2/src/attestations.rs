@@ -0,0 +1,122 @@
+pub mod execute {
+    use cosmwasm_std::{Binary, DepsMut, Env, MessageInfo, Response};
+    use counting_contract_interface::attestation;
+
+    use crate::audit;
+    use crate::contract::execute::{counter_id, donate, load_counter};
+    use crate::error::ContractError;
+    use crate::msg::{Attestation, AuditKind};
+    use crate::signatures::{valid_key, verify, Key};
+    use crate::state::{Verification, ATTESTORS, VERIFIED_DONORS};
+
+    pub fn set_attestor(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        counter_id: Option<String>,
+        name: String,
+        pubkey: Option<Binary>,
+    ) -> Result<Response, ContractError> {
+        let id = self::counter_id(counter_id);
+        let counter = load_counter(deps.storage, &id)?;
+        if info.sender != counter.owner {
+            return Err(ContractError::Unauthorized { owner: counter.owner.to_string() });
+        }
+
+        let previous = ATTESTORS.may_load(deps.storage, (&id, &name))?;
+        match &pubkey {
+            Some(pubkey) if !valid_key(Key::Secp256k1(pubkey)) => return Err(ContractError::InvalidPubkey {}),
+            Some(pubkey) => ATTESTORS.save(deps.storage, (&id, &name), pubkey)?,
+            None => ATTESTORS.remove(deps.storage, (&id, &name)),
+        }
+        let target = format!("{id}/{name}");
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::SetAttestor, Some(target), &previous, &pubkey)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "set_attestor")
+            .add_attribute("counter_id", id)
+            .add_attribute("attestor", name);
+        Ok(resp)
+    }
+
+    pub fn donate_attested(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        counter_id: Option<String>,
+        attestation: Attestation,
+    ) -> Result<Response, ContractError> {
+        let id = self::counter_id(counter_id);
+        load_counter(deps.storage, &id)?;
+        let pubkey = ATTESTORS
+            .may_load(deps.storage, (&id, &attestation.attestor))?
+            .ok_or_else(|| ContractError::AttestorNotFound { name: attestation.attestor.clone() })?;
+
+        let hash = attestation::digest(env.contract.address.as_str(), &id, info.sender.as_str(), &attestation.identity)?;
+        if !verify(deps.api, &hash, &attestation.signature, Key::Secp256k1(&pubkey)) {
+            return Err(ContractError::InvalidAttestation {});
+        }
+        let verification = Verification {
+            attestor: attestation.attestor,
+            identity: attestation.identity,
+            attestation_hash: hash.clone(),
+        };
+        VERIFIED_DONORS.save(deps.storage, (&id, &info.sender), &verification)?;
+
+        let resp = donate(deps, env, info, Some(id))?
+            .add_attribute("attestor", verification.attestor)
+            .add_attribute("attestation_hash", hash.to_hex());
+        Ok(resp)
+    }
+}
+
+pub mod query {
+    use cosmwasm_std::{Deps, Order, StdResult};
+    use cw_storage_plus::Bound;
+
+    use crate::contract::execute::counter_id;
+    use crate::contract::query::page_limit;
+    use crate::msg::{AttestorResp, AttestorsResp, VerifiedDonor, VerifiedDonorsResp};
+    use crate::state::{ATTESTORS, COUNTERS, VERIFIED_DONORS};
+
+    pub fn attestors(deps: Deps, counter_id: Option<String>) -> StdResult<AttestorsResp> {
+        let id = self::counter_id(counter_id);
+        COUNTERS.load(deps.storage, &id)?;
+
+        let attestors = ATTESTORS
+            .prefix(&id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(name, pubkey)| AttestorResp { name, pubkey }))
+            .collect::<StdResult<_>>()?;
+        Ok(AttestorsResp { attestors })
+    }
+
+    pub fn verified_donors(
+        deps: Deps,
+        counter_id: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<VerifiedDonorsResp> {
+        let id = self::counter_id(counter_id);
+        COUNTERS.load(deps.storage, &id)?;
+        let limit = page_limit(limit);
+        let start_after = start_after.map(|addr| deps.api.addr_validate(&addr)).transpose()?;
+        let start = start_after.as_ref().map(Bound::exclusive);
+
+        let donors = VERIFIED_DONORS
+            .prefix(&id)
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                let (address, verification) = item?;
+                Ok(VerifiedDonor {
+                    address,
+                    attestor: verification.attestor,
+                    identity: verification.identity,
+                    attestation_hash: verification.attestation_hash,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+        Ok(VerifiedDonorsResp { donors })
+    }
+}
@@ -0,0 +1,62 @@
+use cosmwasm_std::{to_json_string, Addr, Env, HexBinary, Order, StdResult, Storage};
+use counting_contract_interface::audit;
+use serde::Serialize;
+
+use crate::msg::{AuditEntry, AuditKind};
+use crate::state::AUDIT_LOG;
+
+//appends a privileged action to the log, chained to the previous entry
+pub fn record(
+    storage: &mut dyn Storage,
+    env: &Env,
+    actor: Option<&Addr>,
+    kind: AuditKind,
+    target: Option<String>,
+    before: &impl Serialize,
+    after: &impl Serialize,
+) -> StdResult<()> {
+    let last = AUDIT_LOG
+        .range(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?;
+    let (id, previous) = match last {
+        Some((id, entry)) => (id + 1, entry.digest),
+        None => (0, HexBinary::default()),
+    };
+
+    let mut entry = AuditEntry {
+        id,
+        actor: actor.cloned(),
+        height: env.block.height,
+        time: env.block.time,
+        kind,
+        target,
+        before: to_json_string(before)?,
+        after: to_json_string(after)?,
+        digest: HexBinary::default(),
+    };
+    entry.digest = audit::digest(&entry, &previous)?;
+    AUDIT_LOG.save(storage, id, &entry)
+}
+
+pub mod query {
+    use cosmwasm_std::{Deps, Order, StdResult};
+    use cw_storage_plus::Bound;
+
+    use crate::contract::query::page_limit;
+    use crate::msg::AuditLogResp;
+    use crate::state::AUDIT_LOG;
+
+    pub fn audit_log(deps: Deps, start_after: Option<u64>, limit: Option<u32>) -> StdResult<AuditLogResp> {
+        let limit = page_limit(limit);
+        let start = start_after.map(Bound::exclusive);
+
+        let entries = AUDIT_LOG
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| item.map(|(_, entry)| entry))
+            .collect::<StdResult<_>>()?;
+
+        Ok(AuditLogResp { entries })
+    }
+}
@@ -0,0 +1,1009 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[cfg(feature = "permits")]
+use clap::Args;
+use clap::{Parser, Subcommand, ValueEnum};
+use cosmwasm_schema::generate_api;
+#[cfg(feature = "matching")]
+use cosmwasm_std::Decimal;
+#[cfg(any(feature = "permits", feature = "rounds"))]
+use cosmwasm_std::Timestamp;
+use cosmwasm_std::{from_json, to_json_vec, Binary, Coin, HexBinary, StdError, Uint128};
+#[cfg(feature = "export")]
+use counting_contract::msg::{SnapshotCursor, StateSnapshot};
+use counting_contract::msg::{
+    Attestation, AttestorsResp, AuditLogResp, ConfigResp, CountersResp, DonorTierResp,
+    DonorsByTierResp, ExecuteMsg, FeeConfig, FeeMode, FeeReportResp, InstantiateMsg, QueryMsg,
+    Tier, TierThresholds, TierThresholdsResp, ValueResp, VerifiedDonorsResp, DEFAULT_COUNTER,
+};
+#[cfg(feature = "matching")]
+use counting_contract::msg::MatchingResp;
+#[cfg(feature = "rounds")]
+use counting_contract::msg::RoundResp;
+#[cfg(feature = "permits")]
+use counting_contract::msg::{OwnerPubkey, OwnerPubkeyResp, WithdrawPermit};
+#[cfg(feature = "permits")]
+use counting_contract_interface::permit;
+use counting_contract_interface::{attestation, audit};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Builds, encodes and checks counting contract messages without touching a node
+#[derive(Parser)]
+#[command(name = "counting-cli", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Builds an InstantiateMsg
+    Instantiate {
+        /// Minimal qualifying donation, e.g. 10atom
+        #[arg(long, value_parser = parse_coin)]
+        minimal_donation: Coin,
+        #[arg(long, default_value_t = 0)]
+        counter: u64,
+        /// Owner of the default counter, defaults to the sender
+        #[arg(long)]
+        owner: Option<String>,
+        /// Seconds during which donations can be cancelled
+        #[arg(long)]
+        grace_period: Option<u64>,
+        /// Platform fee in basis points
+        #[arg(long, requires_all = ["fee_collector", "fee_admin"])]
+        fee_bps: Option<u16>,
+        #[arg(long, requires = "fee_bps")]
+        fee_collector: Option<String>,
+        /// Address allowed to change the fee
+        #[arg(long, requires = "fee_bps")]
+        fee_admin: Option<String>,
+        #[arg(long, value_enum, default_value = "donation")]
+        fee_mode: FeeModeArg,
+        /// Json file with a state snapshot exported by another instance
+        #[cfg(feature = "export")]
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+        #[arg(long)]
+        base64: bool,
+    },
+    /// Builds an ExecuteMsg
+    Execute {
+        #[command(subcommand)]
+        msg: ExecuteCommand,
+        #[arg(long, global = true)]
+        base64: bool,
+    },
+    /// Builds a QueryMsg
+    Query {
+        #[command(subcommand)]
+        msg: QueryCommand,
+        #[arg(long, global = true)]
+        base64: bool,
+    },
+    /// Decodes a query response given as json or base64
+    Decode {
+        #[arg(value_enum)]
+        response: ResponseKind,
+        data: String,
+    },
+    /// Validates a json message file against the contract schema
+    Validate {
+        #[arg(value_enum)]
+        kind: MsgKind,
+        file: PathBuf,
+        /// Schema file to validate against instead of the one built into the binary
+        #[arg(long)]
+        schema: Option<PathBuf>,
+    },
+    /// Prints the hex digest an attestor signs to vouch for a donor
+    AttestationDigest {
+        contract: String,
+        donor: String,
+        identity: String,
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    /// Prints the hex digest the counter owner signs to authorize a withdraw permit
+    #[cfg(feature = "permits")]
+    PermitDigest {
+        contract: String,
+        #[command(flatten)]
+        permit: PermitArgs,
+    },
+    /// Checks the hash chain of an AuditLog query response saved as json
+    VerifyAuditLog {
+        file: PathBuf,
+        /// Hex digest of the entry before the first one, when the file doesn't start the log
+        #[arg(long)]
+        previous: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExecuteCommand {
+    CreateCounter {
+        id: String,
+        /// Minimal qualifying donation, e.g. 10atom
+        #[arg(long, value_parser = parse_coin)]
+        minimal_donation: Coin,
+        /// Defaults to the sender
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    Donate {
+        /// Defaults to the counter created at instantiation
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    /// Donates on behalf of the beneficiary
+    DonateFor {
+        #[arg(long)]
+        counter: Option<String>,
+        beneficiary: String,
+    },
+    /// Donates with an attestation of the sender's identity
+    DonateAttested {
+        #[arg(long)]
+        counter: Option<String>,
+        attestor: String,
+        identity: String,
+        /// Hex secp256k1 signature of the attestation digest
+        #[arg(value_parser = parse_hex)]
+        signature: Binary,
+    },
+    /// Refunds a donation still in its grace period
+    CancelDonation {
+        id: u64,
+    },
+    Withdraw {
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    /// Sponsors qualifying donations, send the deposit as funds
+    #[cfg(feature = "matching")]
+    FundMatching {
+        #[arg(long)]
+        counter: Option<String>,
+        /// Matched amount per donated amount, e.g. 0.5
+        #[arg(long)]
+        ratio: Decimal,
+        /// Most matched for a single donation
+        #[arg(long)]
+        cap: Uint128,
+    },
+    #[cfg(feature = "matching")]
+    CloseMatching {
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    /// Opens a quadratic funding round, default counter owner only
+    #[cfg(feature = "rounds")]
+    CreateRound {
+        id: String,
+        #[arg(long)]
+        denom: String,
+        /// Unix time in seconds
+        #[arg(long)]
+        end_time: u64,
+    },
+    #[cfg(feature = "rounds")]
+    RegisterProject {
+        round: String,
+        project: String,
+        #[arg(long)]
+        owner: String,
+    },
+    #[cfg(feature = "rounds")]
+    FundRound {
+        round: String,
+    },
+    #[cfg(feature = "rounds")]
+    DonateToProject {
+        round: String,
+        project: String,
+    },
+    #[cfg(feature = "rounds")]
+    FinalizeRound {
+        round: String,
+    },
+    #[cfg(feature = "rounds")]
+    WithdrawProject {
+        round: String,
+        project: String,
+    },
+    /// Trusts an attestor's hex secp256k1 pubkey, removes the attestor when left out
+    SetAttestor {
+        #[arg(long)]
+        counter: Option<String>,
+        name: String,
+        #[arg(value_parser = parse_hex)]
+        pubkey: Option<Binary>,
+    },
+    /// Sets the hex pubkey signing withdraw permits, disables permits when left out
+    #[cfg(feature = "permits")]
+    SetOwnerPubkey {
+        #[arg(long)]
+        counter: Option<String>,
+        #[arg(long, value_enum, default_value = "secp256k1")]
+        key_type: KeyTypeArg,
+        #[arg(value_parser = parse_hex)]
+        pubkey: Option<Binary>,
+    },
+    /// Withdraws with a permit signed by the counter owner
+    #[cfg(feature = "permits")]
+    WithdrawWithPermit {
+        #[command(flatten)]
+        permit: PermitArgs,
+        /// Hex signature of the permit digest
+        #[arg(long, value_parser = parse_hex)]
+        signature: Binary,
+    },
+    /// Imports the next page of a snapshot, sender of the instantiation only
+    #[cfg(feature = "export")]
+    ImportState {
+        /// Json file with the page
+        snapshot: PathBuf,
+    },
+    /// Replaces the platform fee, fee admin only
+    UpdateFee {
+        bps: u16,
+        collector: String,
+        admin: String,
+        #[arg(long, value_enum, default_value = "donation")]
+        mode: FeeModeArg,
+    },
+    /// Sets the cumulative donations in denom needed for each tier
+    SetTierThresholds {
+        #[arg(long)]
+        counter: Option<String>,
+        denom: String,
+        /// Bronze, silver and gold thresholds, removes them when left out
+        #[arg(num_args = 3)]
+        thresholds: Vec<Uint128>,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueryCommand {
+    Value {
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    /// Value once the block at height was executed
+    ValueAtHeight {
+        #[arg(long)]
+        counter: Option<String>,
+        height: u64,
+    },
+    Config {
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    Counters {
+        #[arg(long)]
+        start_after: Option<String>,
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+    #[cfg(feature = "matching")]
+    Matching {
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    #[cfg(feature = "rounds")]
+    Round {
+        round: String,
+    },
+    TierThresholds {
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    DonorTier {
+        #[arg(long)]
+        counter: Option<String>,
+        address: String,
+    },
+    DonorsByTier {
+        #[arg(long)]
+        counter: Option<String>,
+        #[arg(value_enum)]
+        tier: TierArg,
+        #[arg(long)]
+        start_after: Option<String>,
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+    Attestors {
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    VerifiedDonors {
+        #[arg(long)]
+        counter: Option<String>,
+        #[arg(long)]
+        start_after: Option<String>,
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+    FeeReport,
+    #[cfg(feature = "permits")]
+    OwnerPubkey {
+        #[arg(long)]
+        counter: Option<String>,
+    },
+    AuditLog {
+        #[arg(long)]
+        start_after: Option<u64>,
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+    #[cfg(feature = "export")]
+    ExportState {
+        /// Next of the previous page, as json
+        #[arg(long, value_parser = parse_cursor)]
+        start_after: Option<SnapshotCursor>,
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ResponseKind {
+    Value,
+    Config,
+    Counters,
+    #[cfg(feature = "matching")]
+    Matching,
+    #[cfg(feature = "rounds")]
+    Round,
+    TierThresholds,
+    DonorTier,
+    DonorsByTier,
+    Attestors,
+    VerifiedDonors,
+    FeeReport,
+    #[cfg(feature = "permits")]
+    OwnerPubkey,
+    AuditLog,
+    #[cfg(feature = "export")]
+    ExportState,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TierArg {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl From<TierArg> for Tier {
+    fn from(tier: TierArg) -> Self {
+        match tier {
+            TierArg::Bronze => Tier::Bronze,
+            TierArg::Silver => Tier::Silver,
+            TierArg::Gold => Tier::Gold,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FeeModeArg {
+    Donation,
+    Withdraw,
+}
+
+impl From<FeeModeArg> for FeeMode {
+    fn from(mode: FeeModeArg) -> Self {
+        match mode {
+            FeeModeArg::Donation => FeeMode::Donation,
+            FeeModeArg::Withdraw => FeeMode::Withdraw,
+        }
+    }
+}
+
+#[cfg(feature = "permits")]
+#[derive(Clone, Copy, ValueEnum)]
+enum KeyTypeArg {
+    Secp256k1,
+    Ed25519,
+}
+
+#[cfg(feature = "permits")]
+#[derive(Args)]
+struct PermitArgs {
+    #[arg(long)]
+    counter: Option<String>,
+    receiver: String,
+    nonce: u64,
+    /// Unix time in seconds the permit expires at
+    expires: u64,
+    /// Coins to withdraw, e.g. 10atom, repeated for more denoms
+    #[arg(long, value_parser = parse_coin)]
+    amount: Vec<Coin>,
+}
+
+#[cfg(feature = "permits")]
+impl From<PermitArgs> for WithdrawPermit {
+    fn from(args: PermitArgs) -> Self {
+        WithdrawPermit {
+            counter_id: args.counter,
+            receiver: args.receiver,
+            amount: args.amount,
+            nonce: args.nonce,
+            expires: Timestamp::from_seconds(args.expires),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MsgKind {
+    Instantiate,
+    Execute,
+    Query,
+}
+
+impl MsgKind {
+    fn schema_key(self) -> &'static str {
+        match self {
+            MsgKind::Instantiate => "instantiate",
+            MsgKind::Execute => "execute",
+            MsgKind::Query => "query",
+        }
+    }
+}
+
+//parses coins written the way wasmd expects them, amount directly followed by denom
+fn parse_coin(s: &str) -> Result<Coin, String> {
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing denom in {s}"))?;
+    let (amount, denom) = s.split_at(split);
+    if amount.is_empty() {
+        return Err(format!("missing amount in {s}"));
+    }
+    let amount: Uint128 = amount.parse().map_err(|err: StdError| err.to_string())?;
+    Ok(Coin::new(amount.u128(), denom))
+}
+
+fn parse_hex(s: &str) -> Result<Binary, String> {
+    HexBinary::from_hex(s)
+        .map(Binary::from)
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(feature = "export")]
+fn parse_cursor(s: &str) -> Result<SnapshotCursor, String> {
+    serde_json::from_str(s).map_err(|err| err.to_string())
+}
+
+fn render(msg: &impl Serialize, base64: bool) -> Result<String, String> {
+    let json = to_json_vec(msg).map_err(|err| err.to_string())?;
+    if base64 {
+        Ok(Binary::from(json).to_base64())
+    } else {
+        String::from_utf8(json).map_err(|err| err.to_string())
+    }
+}
+
+//responses come either as plain json or base64 encoded as in `wasmd query` output
+fn decode_data(data: &str) -> Result<Vec<u8>, String> {
+    let data = data.trim();
+    if data.starts_with('{') {
+        Ok(data.as_bytes().to_vec())
+    } else {
+        Binary::from_base64(data)
+            .map(Binary::into)
+            .map_err(|err| err.to_string())
+    }
+}
+
+fn decode(response: ResponseKind, data: &str) -> Result<String, String> {
+    let data = decode_data(data)?;
+    let value = match response {
+        ResponseKind::Value => {
+            serde_json::to_value(from_json::<ValueResp>(&data).map_err(|err| err.to_string())?)
+        }
+        ResponseKind::Config => {
+            serde_json::to_value(from_json::<ConfigResp>(&data).map_err(|err| err.to_string())?)
+        }
+        ResponseKind::Counters => {
+            serde_json::to_value(from_json::<CountersResp>(&data).map_err(|err| err.to_string())?)
+        }
+        #[cfg(feature = "matching")]
+        ResponseKind::Matching => {
+            serde_json::to_value(from_json::<MatchingResp>(&data).map_err(|err| err.to_string())?)
+        }
+        #[cfg(feature = "rounds")]
+        ResponseKind::Round => {
+            serde_json::to_value(from_json::<RoundResp>(&data).map_err(|err| err.to_string())?)
+        }
+        ResponseKind::TierThresholds => serde_json::to_value(
+            from_json::<TierThresholdsResp>(&data).map_err(|err| err.to_string())?,
+        ),
+        ResponseKind::DonorTier => {
+            serde_json::to_value(from_json::<DonorTierResp>(&data).map_err(|err| err.to_string())?)
+        }
+        ResponseKind::DonorsByTier => serde_json::to_value(
+            from_json::<DonorsByTierResp>(&data).map_err(|err| err.to_string())?,
+        ),
+        ResponseKind::Attestors => {
+            serde_json::to_value(from_json::<AttestorsResp>(&data).map_err(|err| err.to_string())?)
+        }
+        ResponseKind::VerifiedDonors => serde_json::to_value(
+            from_json::<VerifiedDonorsResp>(&data).map_err(|err| err.to_string())?,
+        ),
+        ResponseKind::FeeReport => {
+            serde_json::to_value(from_json::<FeeReportResp>(&data).map_err(|err| err.to_string())?)
+        }
+        #[cfg(feature = "permits")]
+        ResponseKind::OwnerPubkey => serde_json::to_value(
+            from_json::<OwnerPubkeyResp>(&data).map_err(|err| err.to_string())?,
+        ),
+        ResponseKind::AuditLog => {
+            serde_json::to_value(from_json::<AuditLogResp>(&data).map_err(|err| err.to_string())?)
+        }
+        #[cfg(feature = "export")]
+        ResponseKind::ExportState => serde_json::to_value(
+            from_json::<StateSnapshot>(&data).map_err(|err| err.to_string())?,
+        ),
+    }
+    .map_err(|err| err.to_string())?;
+    serde_json::to_string_pretty(&value).map_err(|err| err.to_string())
+}
+
+fn builtin_schema() -> Value {
+    let api = generate_api! {
+        instantiate: InstantiateMsg,
+        execute: ExecuteMsg,
+        query: QueryMsg,
+    }
+    .render();
+    serde_json::to_value(api).expect("schema is serializable")
+}
+
+fn read_json(path: &PathBuf) -> Result<Value, String> {
+    let content =
+        std::fs::read(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    serde_json::from_slice(&content).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+#[cfg(feature = "export")]
+fn read_snapshot(path: &PathBuf) -> Result<StateSnapshot, String> {
+    serde_json::from_value(read_json(path)?).map_err(|err| format!("{}: {err}", path.display()))
+}
+
+fn verify_audit_log(file: &PathBuf, previous: Option<&str>) -> Result<String, String> {
+    let log: AuditLogResp = serde_json::from_value(read_json(file)?)
+        .map_err(|err| format!("{}: {err}", file.display()))?;
+    let previous = previous
+        .map(HexBinary::from_hex)
+        .transpose()
+        .map_err(|err| format!("previous digest: {err}"))?;
+    audit::verify(&log.entries, previous.as_ref())
+        .map_err(|id| format!("audit log entry {id} doesn't match the hash chain"))?;
+    Ok(format!("{} audit log entries verified", log.entries.len()))
+}
+
+//checks msg against the section of api for kind, every violation on its own line
+fn check_schema(kind: MsgKind, api: &Value, msg: &Value) -> Result<(), String> {
+    let schema = api
+        .get(kind.schema_key())
+        .ok_or_else(|| format!("schema has no {} section", kind.schema_key()))?;
+    let validator = jsonschema::validator_for(schema).map_err(|err| err.to_string())?;
+
+    let errors: Vec<_> = validator
+        .iter_errors(msg)
+        .map(|err| match err.instance_path.to_string() {
+            path if path.is_empty() => err.to_string(),
+            path => format!("{path}: {err}"),
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    Ok(())
+}
+
+fn validate(kind: MsgKind, file: &PathBuf, schema: Option<&PathBuf>) -> Result<String, String> {
+    let api = match schema {
+        Some(path) => read_json(path)?,
+        None => builtin_schema(),
+    };
+    check_schema(kind, &api, &read_json(file)?)?;
+    Ok(format!("{} is a valid {} message", file.display(), kind.schema_key()))
+}
+
+fn run(cli: Cli) -> Result<String, String> {
+    match cli.command {
+        Command::Instantiate {
+            minimal_donation,
+            counter,
+            owner,
+            grace_period,
+            fee_bps,
+            fee_collector,
+            fee_admin,
+            fee_mode,
+            #[cfg(feature = "export")]
+            snapshot,
+            base64,
+        } => {
+            //clap makes sure collector and admin come with the bps
+            let fee = fee_bps.map(|bps| FeeConfig {
+                bps,
+                collector: fee_collector.unwrap_or_default(),
+                admin: fee_admin.unwrap_or_default(),
+                mode: fee_mode.into(),
+            });
+            let msg = InstantiateMsg {
+                counter,
+                owner,
+                grace_period,
+                fee,
+                #[cfg(feature = "export")]
+                snapshot: snapshot.as_ref().map(read_snapshot).transpose()?,
+                ..InstantiateMsg::new(minimal_donation)
+            };
+            render(&msg, base64)
+        }
+        Command::Execute { msg, base64 } => {
+            let msg = match msg {
+                ExecuteCommand::CreateCounter {
+                    id,
+                    minimal_donation,
+                    owner,
+                } => ExecuteMsg::CreateCounter {
+                    id,
+                    minimal_donation,
+                    owner,
+                },
+                ExecuteCommand::Donate { counter } => ExecuteMsg::Donate {
+                    counter_id: counter,
+                },
+                ExecuteCommand::DonateFor {
+                    counter,
+                    beneficiary,
+                } => ExecuteMsg::DonateFor {
+                    counter_id: counter,
+                    beneficiary,
+                },
+                ExecuteCommand::DonateAttested {
+                    counter,
+                    attestor,
+                    identity,
+                    signature,
+                } => ExecuteMsg::DonateAttested {
+                    counter_id: counter,
+                    attestation: Attestation {
+                        attestor,
+                        identity,
+                        signature,
+                    },
+                },
+                ExecuteCommand::CancelDonation { id } => ExecuteMsg::CancelDonation { id },
+                ExecuteCommand::Withdraw { counter } => ExecuteMsg::Withdraw {
+                    counter_id: counter,
+                },
+                #[cfg(feature = "matching")]
+                ExecuteCommand::FundMatching {
+                    counter,
+                    ratio,
+                    cap,
+                } => ExecuteMsg::FundMatching {
+                    counter_id: counter,
+                    ratio,
+                    cap,
+                },
+                #[cfg(feature = "matching")]
+                ExecuteCommand::CloseMatching { counter } => ExecuteMsg::CloseMatching {
+                    counter_id: counter,
+                },
+                #[cfg(feature = "rounds")]
+                ExecuteCommand::CreateRound {
+                    id,
+                    denom,
+                    end_time,
+                } => ExecuteMsg::CreateRound {
+                    id,
+                    denom,
+                    end_time: Timestamp::from_seconds(end_time),
+                },
+                #[cfg(feature = "rounds")]
+                ExecuteCommand::RegisterProject {
+                    round,
+                    project,
+                    owner,
+                } => ExecuteMsg::RegisterProject {
+                    round_id: round,
+                    project_id: project,
+                    owner,
+                },
+                #[cfg(feature = "rounds")]
+                ExecuteCommand::FundRound { round } => ExecuteMsg::FundRound { round_id: round },
+                #[cfg(feature = "rounds")]
+                ExecuteCommand::DonateToProject { round, project } => ExecuteMsg::DonateToProject {
+                    round_id: round,
+                    project_id: project,
+                },
+                #[cfg(feature = "rounds")]
+                ExecuteCommand::FinalizeRound { round } => {
+                    ExecuteMsg::FinalizeRound { round_id: round }
+                }
+                #[cfg(feature = "rounds")]
+                ExecuteCommand::WithdrawProject { round, project } => ExecuteMsg::WithdrawProject {
+                    round_id: round,
+                    project_id: project,
+                },
+                ExecuteCommand::SetAttestor {
+                    counter,
+                    name,
+                    pubkey,
+                } => ExecuteMsg::SetAttestor {
+                    counter_id: counter,
+                    name,
+                    pubkey,
+                },
+                #[cfg(feature = "permits")]
+                ExecuteCommand::SetOwnerPubkey {
+                    counter,
+                    key_type,
+                    pubkey,
+                } => ExecuteMsg::SetOwnerPubkey {
+                    counter_id: counter,
+                    pubkey: pubkey.map(|pubkey| match key_type {
+                        KeyTypeArg::Secp256k1 => OwnerPubkey::Secp256k1(pubkey),
+                        KeyTypeArg::Ed25519 => OwnerPubkey::Ed25519(pubkey),
+                    }),
+                },
+                #[cfg(feature = "permits")]
+                ExecuteCommand::WithdrawWithPermit { permit, signature } => {
+                    ExecuteMsg::WithdrawWithPermit {
+                        permit: permit.into(),
+                        signature,
+                    }
+                }
+                #[cfg(feature = "export")]
+                ExecuteCommand::ImportState { snapshot } => ExecuteMsg::ImportState {
+                    snapshot: Box::new(read_snapshot(&snapshot)?),
+                },
+                ExecuteCommand::UpdateFee {
+                    bps,
+                    collector,
+                    admin,
+                    mode,
+                } => ExecuteMsg::UpdateFee {
+                    fee: FeeConfig {
+                        bps,
+                        collector,
+                        admin,
+                        mode: mode.into(),
+                    },
+                },
+                ExecuteCommand::SetTierThresholds {
+                    counter,
+                    denom,
+                    thresholds,
+                } => ExecuteMsg::SetTierThresholds {
+                    counter_id: counter,
+                    denom,
+                    thresholds: match thresholds[..] {
+                        [bronze, silver, gold] => Some(TierThresholds {
+                            bronze,
+                            silver,
+                            gold,
+                        }),
+                        _ => None,
+                    },
+                },
+            };
+            render(&msg, base64)
+        }
+        Command::Query { msg, base64 } => {
+            let msg = match msg {
+                QueryCommand::Value { counter } => QueryMsg::Value {
+                    counter_id: counter,
+                },
+                QueryCommand::ValueAtHeight { counter, height } => QueryMsg::ValueAtHeight {
+                    counter_id: counter,
+                    height,
+                },
+                QueryCommand::Config { counter } => QueryMsg::Config {
+                    counter_id: counter,
+                },
+                QueryCommand::Counters { start_after, limit } => {
+                    QueryMsg::Counters { start_after, limit }
+                }
+                #[cfg(feature = "matching")]
+                QueryCommand::Matching { counter } => QueryMsg::Matching {
+                    counter_id: counter,
+                },
+                #[cfg(feature = "rounds")]
+                QueryCommand::Round { round } => QueryMsg::Round { round_id: round },
+                QueryCommand::Attestors { counter } => QueryMsg::Attestors {
+                    counter_id: counter,
+                },
+                QueryCommand::VerifiedDonors {
+                    counter,
+                    start_after,
+                    limit,
+                } => QueryMsg::VerifiedDonors {
+                    counter_id: counter,
+                    start_after,
+                    limit,
+                },
+                QueryCommand::FeeReport => QueryMsg::FeeReport {},
+                #[cfg(feature = "permits")]
+                QueryCommand::OwnerPubkey { counter } => QueryMsg::OwnerPubkey {
+                    counter_id: counter,
+                },
+                QueryCommand::AuditLog { start_after, limit } => {
+                    QueryMsg::AuditLog { start_after, limit }
+                }
+                QueryCommand::TierThresholds { counter } => QueryMsg::TierThresholds {
+                    counter_id: counter,
+                },
+                QueryCommand::DonorTier { counter, address } => QueryMsg::DonorTier {
+                    counter_id: counter,
+                    address,
+                },
+                QueryCommand::DonorsByTier {
+                    counter,
+                    tier,
+                    start_after,
+                    limit,
+                } => QueryMsg::DonorsByTier {
+                    counter_id: counter,
+                    tier: tier.into(),
+                    start_after,
+                    limit,
+                },
+                #[cfg(feature = "export")]
+                QueryCommand::ExportState { start_after, limit } => {
+                    QueryMsg::ExportState { start_after, limit }
+                }
+            };
+            render(&msg, base64)
+        }
+        Command::Decode { response, data } => decode(response, &data),
+        Command::Validate { kind, file, schema } => validate(kind, &file, schema.as_ref()),
+        Command::AttestationDigest {
+            contract,
+            donor,
+            identity,
+            counter,
+        } => {
+            let counter = counter.as_deref().unwrap_or(DEFAULT_COUNTER);
+            attestation::digest(&contract, counter, &donor, &identity)
+                .map(|digest| digest.to_hex())
+                .map_err(|err| err.to_string())
+        }
+        #[cfg(feature = "permits")]
+        Command::PermitDigest { contract, permit: args } => {
+            permit::digest(&contract, &args.into())
+                .map(|digest| digest.to_hex())
+                .map_err(|err| err.to_string())
+        }
+        Command::VerifyAuditLog { file, previous } => verify_audit_log(&file, previous.as_deref()),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(output) => {
+            println!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn build(args: &[&str]) -> String {
+        let cli = Cli::try_parse_from(["counting-cli"].iter().chain(args)).unwrap();
+        run(cli).unwrap()
+    }
+
+    fn build_json(args: &[&str]) -> Value {
+        serde_json::from_str(&build(args)).unwrap()
+    }
+
+    #[test]
+    fn built_messages_match_schema() {
+        let api = builtin_schema();
+        let cases: &[(MsgKind, &[&str])] = &[
+            (MsgKind::Instantiate, &["instantiate", "--minimal-donation", "10atom"]),
+            (
+                MsgKind::Instantiate,
+                &[
+                    "instantiate", "--minimal-donation", "10atom", "--grace-period", "60", "--fee-bps", "100",
+                    "--fee-collector", "collector", "--fee-admin", "admin", "--fee-mode", "withdraw",
+                ],
+            ),
+            (MsgKind::Execute, &["execute", "create-counter", "named", "--minimal-donation", "5atom"]),
+            (MsgKind::Execute, &["execute", "donate"]),
+            (MsgKind::Execute, &["execute", "donate", "--counter", "named"]),
+            (MsgKind::Execute, &["execute", "donate-for", "alice"]),
+            (MsgKind::Execute, &["execute", "donate-attested", "kyc", "alice@example.com", "0a0b"]),
+            (MsgKind::Execute, &["execute", "withdraw"]),
+            (MsgKind::Execute, &["execute", "set-attestor", "kyc"]),
+            (MsgKind::Execute, &["execute", "update-fee", "100", "collector", "admin"]),
+            (MsgKind::Execute, &["execute", "set-tier-thresholds", "atom", "1", "2", "3"]),
+            #[cfg(feature = "matching")]
+            (MsgKind::Execute, &["execute", "fund-matching", "--ratio", "0.5", "--cap", "10"]),
+            #[cfg(feature = "rounds")]
+            (MsgKind::Execute, &["execute", "create-round", "r1", "--denom", "atom", "--end-time", "100"]),
+            #[cfg(feature = "permits")]
+            (MsgKind::Execute, &["execute", "set-owner-pubkey", "--key-type", "ed25519", "0a0b"]),
+            #[cfg(feature = "permits")]
+            (
+                MsgKind::Execute,
+                &["execute", "withdraw-with-permit", "bob", "0", "100", "--amount", "5atom", "--signature", "0a0b"],
+            ),
+            (MsgKind::Query, &["query", "value"]),
+            (MsgKind::Query, &["query", "value-at-height", "--counter", "named", "10"]),
+            (MsgKind::Query, &["query", "counters", "--limit", "5"]),
+            (MsgKind::Query, &["query", "donors-by-tier", "gold"]),
+            (MsgKind::Query, &["query", "fee-report"]),
+            (MsgKind::Query, &["query", "audit-log", "--start-after", "3"]),
+            #[cfg(feature = "export")]
+            (MsgKind::Query, &["query", "export-state"]),
+            #[cfg(feature = "export")]
+            (
+                MsgKind::Query,
+                &["query", "export-state", "--start-after", r#"{"list":"donors","key":"AAE="}"#, "--limit", "50"],
+            ),
+        ];
+        for (kind, args) in cases {
+            check_schema(*kind, &api, &build_json(args)).unwrap_or_else(|err| panic!("{args:?}: {err}"));
+        }
+    }
+
+    #[test]
+    fn schema_rejects_invalid_messages() {
+        let api = builtin_schema();
+        check_schema(MsgKind::Execute, &api, &json!({ "donate": { "counter_id": 5 } })).unwrap_err();
+        check_schema(MsgKind::Query, &api, &json!({ "donate": {} })).unwrap_err();
+    }
+
+    #[test]
+    fn default_counter_and_missing_options_are_left_out() {
+        assert_eq!(build_json(&["execute", "donate"]), json!({ "donate": {} }));
+        assert_eq!(build_json(&["query", "config"]), json!({ "config": {} }));
+        assert_eq!(
+            build_json(&["instantiate", "--minimal-donation", "10atom"]),
+            json!({ "counter": 0, "minimal_donation": { "denom": "atom", "amount": "10" } })
+        );
+    }
+
+    #[test]
+    fn base64_encodes_the_same_message() {
+        let json = build(&["execute", "donate", "--counter", "named"]);
+        let encoded = build(&["execute", "donate", "--counter", "named", "--base64"]);
+        assert_eq!(Binary::from_base64(&encoded).unwrap().as_slice(), json.as_bytes());
+    }
+
+    #[test]
+    fn decodes_json_and_base64_responses() {
+        let json = r#"{"value":3}"#;
+        let encoded = Binary::from(json.as_bytes()).to_base64();
+        let decoded = decode(ResponseKind::Value, json).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&decoded).unwrap(), json!({ "value": 3 }));
+        assert_eq!(decode(ResponseKind::Value, &encoded).unwrap(), decoded);
+        //surrounding whitespace, as left by shell pipes, is ignored
+        assert_eq!(decode(ResponseKind::Value, &format!(" {encoded}\n")).unwrap(), decoded);
+
+        decode(ResponseKind::Value, r#"{"counter":3}"#).unwrap_err();
+        decode(ResponseKind::Value, "not base64!").unwrap_err();
+    }
+}
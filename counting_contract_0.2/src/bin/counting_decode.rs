@@ -0,0 +1,40 @@
+use std::io::Read;
+use std::process::ExitCode;
+
+use counting_contract::decoder::{decode, parse_dump};
+
+fn main() -> ExitCode {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: counting-decode <dump.json|->");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut data = vec![];
+    let read = if path == "-" {
+        std::io::stdin().read_to_end(&mut data).map(|_| ())
+    } else {
+        std::fs::read(&path).map(|content| data = content)
+    };
+    if let Err(err) = read {
+        eprintln!("error: cannot read {path}: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    let records = match parse_dump(&data) {
+        Ok(records) => records,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let dump = decode(&records);
+    println!("{}", serde_json::to_string_pretty(&dump).unwrap());
+    for entry in dump.flagged() {
+        eprintln!("warning: {:?} key {}", entry.status, entry.key);
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use counting_contract::multitest::scenario::Scenario;
+
+fn main() -> ExitCode {
+    let paths: Vec<PathBuf> = std::env::args_os().skip(1).map(PathBuf::from).collect();
+    if paths.is_empty() {
+        eprintln!("usage: counting-scenario <scenario.json|scenario.toml>...");
+        return ExitCode::FAILURE;
+    }
+
+    let mut passed = true;
+    for path in paths {
+        match Scenario::load(&path) {
+            Ok(scenario) => {
+                let report = scenario.run();
+                println!("{report}");
+                passed &= report.passed();
+            }
+            Err(err) => {
+                eprintln!("error: {err}");
+                passed = false;
+            }
+        }
+    }
+
+    if passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
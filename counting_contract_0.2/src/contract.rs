@@ -1,83 +1,1010 @@
-use crate::state::{State, STATE, OWNER};
-use crate::msg::{InstantiateMsg};
-use cosmwasm_std::{Coin, DepsMut, Response, StdResult, MessageInfo};
+use crate::state::{CounterState, COUNTERS, COUNTER_VALUES, FEE, HISTORY_START, GRACE_PERIOD, LEGACY_COUNTER, LEGACY_MINIMAL_DONATION, LEGACY_OWNER, LEGACY_STATE};
+use crate::{audit, fees};
+use crate::msg::{AuditKind, InstantiateMsg, MigrateMsg, DEFAULT_COUNTER};
+use crate::error::ContractError;
+use cosmwasm_std::{DepsMut, Env, Order, Response, StdResult, MessageInfo, Storage};
+#[cfg(feature = "matching")]
+use cosmwasm_std::Decimal;
 use cw2::set_contract_version;
-use cw_storage_plus::Item;
 
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+//cosmos sdk refuses longer denoms anyway
+#[cfg(any(feature = "export", feature = "rounds"))]
+pub(crate) const MAX_DENOM_LEN: usize = 128;
+//entries of a snapshot page over all lists, keeps importing one within the gas of a transaction
+#[cfg(feature = "export")]
+pub(crate) const MAX_SNAPSHOT_ENTRIES: usize = 200;
+const MAX_ID_LEN: usize = 64;
+//a sponsor multiplies a donation at most a hundredfold
+#[cfg(feature = "matching")]
+pub(crate) const MAX_MATCHING_RATIO: Decimal = Decimal::percent(10_000);
+//keeps matching a donation within the gas of a single execute
+#[cfg(feature = "matching")]
+pub(crate) const MAX_ACTIVE_SPONSORS: u32 = 20;
 
-pub fn instantiate(deps: DepsMut, info: MessageInfo, msg: InstantiateMsg) -> StdResult<Response> {
+pub fn instantiate(deps: DepsMut, env: Env, info: MessageInfo, msg: InstantiateMsg) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
-    STATE.save(deps.storage, &State {counter: 0, minimal_donation: msg.minimal_donation})?;
-    OWNER.save(deps.storage, &info.sender)?;
+    HISTORY_START.save(deps.storage, &env.block.height)?;
+    if let Some(grace_period) = msg.grace_period.filter(|seconds| *seconds > 0) {
+        GRACE_PERIOD.save(deps.storage, &grace_period)?;
+    }
+    if let Some(fee) = msg.fee {
+        FEE.save(deps.storage, &fees::validate(deps.api, fee)?)?;
+    }
+
+    #[cfg(feature = "export")]
+    if let Some(snapshot) = msg.snapshot {
+        return snapshot::import(deps, env, info, msg.minimal_donation, snapshot);
+    }
+
+    let owner = match msg.owner {
+        Some(owner) => deps.api.addr_validate(&owner)?,
+        None => info.sender,
+    };
+    let counter = CounterState {
+        counter: 0,
+        minimal_donation: msg.minimal_donation,
+        owner,
+        balance: vec![],
+    };
+    save_counter(deps.storage, DEFAULT_COUNTER, &counter, env.block.height)?;
     Ok(Response::new())
 }
 
-pub fn migrate(deps: DepsMut) -> StdResult<Response> {
-    pub const COUNTER: Item<u64> = Item::new("counter");
-    pub const MINIMAL_DONATION: Item<Coin> = Item::new("minimal_donation");
+//for counters whose value changed, keeps their history along
+pub(crate) fn save_counter(storage: &mut dyn Storage, id: &str, counter: &CounterState, height: u64) -> StdResult<()> {
+    COUNTERS.save(storage, id, counter)?;
+    COUNTER_VALUES.save(storage, id, &counter.counter, height)
+}
+
+//ids of counters, rounds and projects
+pub(crate) fn validate_id(id: &str) -> Result<(), ContractError> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+    if id.is_empty() || id.len() > MAX_ID_LEN || !id.chars().all(valid_char) {
+        return Err(ContractError::InvalidId { id: id.to_owned() });
+    }
+    Ok(())
+}
+
+#[cfg(feature = "export")]
+mod snapshot {
+    use cosmwasm_std::{Addr, Coin, Deps, DepsMut, Empty, Env, MessageInfo, Order, Response, StdResult};
+    use counting_contract_interface::audit;
+    use cw_storage_plus::{Bound, KeyDeserialize, Map, PrimaryKey};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+
+    use super::{save_counter, validate_id, CONTRACT_NAME, CONTRACT_VERSION, MAX_DENOM_LEN, MAX_SNAPSHOT_ENTRIES};
+    use crate::error::ContractError;
+    #[cfg(feature = "permits")]
+    use crate::msg::OwnerPubkeySnapshot;
+    use crate::msg::{
+        AttestorSnapshot, ContractInfoSnapshot, CounterSnapshot, DonorSnapshot, FeeConfig, SnapshotCursor,
+        SnapshotList, StateSnapshot, TierThresholdsSnapshot, VerifiedDonorSnapshot, DEFAULT_COUNTER, SNAPSHOT_VERSION,
+    };
+    use crate::signatures::{valid_key, Key};
+    #[cfg(feature = "permits")]
+    use crate::state::OWNER_PUBKEYS;
+    use crate::state::{
+        add_coins, CounterState, Donor, Import, Verification, ATTESTORS, AUDIT_LOG, COUNTERS, DONORS, DONORS_BY_TIER,
+        FEE, FEES_COLLECTED, GRACE_PERIOD, IMPORT, TIER_THRESHOLDS, VERIFIED_DONORS,
+    };
+    use crate::{fees, tiers};
+
+    //entries of a page, taken list by list in the order of SnapshotList
+    struct Page {
+        start_after: Option<SnapshotCursor>,
+        left: usize,
+        last: Option<SnapshotCursor>,
+        next: Option<SnapshotCursor>,
+    }
+
+    impl Page {
+        fn take<'a, K, T, E>(
+            &mut self,
+            deps: Deps,
+            map: &Map<'a, K, T>,
+            list: SnapshotList,
+            mut entry: impl FnMut(K::Output, T) -> Option<E>,
+        ) -> StdResult<Vec<E>>
+        where
+            K: PrimaryKey<'a> + KeyDeserialize,
+            T: Serialize + DeserializeOwned,
+        {
+            let mut entries = vec![];
+            let start = match &self.start_after {
+                _ if self.next.is_some() => return Ok(entries),
+                Some(cursor) if cursor.list > list => return Ok(entries),
+                Some(cursor) if cursor.list == list => Some(Bound::ExclusiveRaw(cursor.key.to_vec())),
+                _ => None,
+            };
+            for item in map.range_raw(deps.storage, start, None, Order::Ascending) {
+                let (key, value) = item?;
+                let Some(entry) = entry(K::from_vec(key.clone())?, value) else {
+                    continue;
+                };
+                //only a page followed by more entries gets a next
+                if self.left == 0 {
+                    self.next = self.last.clone();
+                    break;
+                }
+                entries.push(entry);
+                self.left -= 1;
+                self.last = Some(SnapshotCursor { list, key: key.into() });
+            }
+            Ok(entries)
+        }
+    }
+
+    //the exporting contract should take no messages while its pages are queried, or they may
+    //miss what changed in between
+    pub fn export(deps: Deps, start_after: Option<SnapshotCursor>, limit: Option<u32>) -> StdResult<StateSnapshot> {
+        let default = COUNTERS.load(deps.storage, DEFAULT_COUNTER)?;
+        let info = cw2::get_contract_version(deps.storage)?;
+        let limit = limit.map_or(MAX_SNAPSHOT_ENTRIES, |limit| (limit as usize).clamp(1, MAX_SNAPSHOT_ENTRIES));
+        let mut page = Page { start_after: start_after.clone(), left: limit, last: None, next: None };
+
+        let counters = page.take(deps, &COUNTERS, SnapshotList::Counters, |id, counter| {
+            (id != DEFAULT_COUNTER).then(|| CounterSnapshot {
+                id,
+                owner: counter.owner.into_string(),
+                counter: counter.counter,
+                minimal_donation: counter.minimal_donation,
+            })
+        })?;
+        let tier_thresholds = page.take(deps, &TIER_THRESHOLDS, SnapshotList::TierThresholds, |key, thresholds| {
+            let (counter_id, denom) = key;
+            Some(TierThresholdsSnapshot { counter_id, denom, thresholds })
+        })?;
+        let donors = page.take(deps, &DONORS, SnapshotList::Donors, |(counter_id, address), donor| {
+            Some(DonorSnapshot {
+                counter_id,
+                address: address.into_string(),
+                donated: donor.donated,
+                tier: donor.tier,
+            })
+        })?;
+        let attestors = page.take(deps, &ATTESTORS, SnapshotList::Attestors, |(counter_id, name), pubkey| {
+            Some(AttestorSnapshot { counter_id, name, pubkey })
+        })?;
+        let verified_donors = page.take(
+            deps,
+            &VERIFIED_DONORS,
+            SnapshotList::VerifiedDonors,
+            |(counter_id, address), verification| {
+                Some(VerifiedDonorSnapshot {
+                    counter_id,
+                    address: address.into_string(),
+                    attestor: verification.attestor,
+                    identity: verification.identity,
+                    attestation_hash: verification.attestation_hash,
+                })
+            },
+        )?;
+        #[cfg(feature = "permits")]
+        let owner_pubkeys = page.take(deps, &OWNER_PUBKEYS, SnapshotList::OwnerPubkeys, |counter_id, pubkey| {
+            Some(OwnerPubkeySnapshot { counter_id, pubkey })
+        })?;
+        let audit_log = page.take(deps, &AUDIT_LOG, SnapshotList::AuditLog, |_, entry| Some(entry))?;
+
+        let fee = FEE.may_load(deps.storage)?.map(|fee| FeeConfig {
+            bps: fee.bps,
+            collector: fee.collector.into_string(),
+            admin: fee.admin.into_string(),
+            mode: fee.mode,
+        });
+        Ok(StateSnapshot {
+            version: SNAPSHOT_VERSION,
+            contract_info: ContractInfoSnapshot {
+                contract: info.contract,
+                version: info.version,
+            },
+            owner: default.owner.into_string(),
+            counter: default.counter,
+            minimal_donation: default.minimal_donation,
+            counters,
+            grace_period: GRACE_PERIOD.may_load(deps.storage)?,
+            fee,
+            fees_collected: FEES_COLLECTED.may_load(deps.storage)?.unwrap_or_default(),
+            tier_thresholds,
+            donors,
+            attestors,
+            verified_donors,
+            #[cfg(feature = "permits")]
+            owner_pubkeys,
+            audit_log,
+            start_after,
+            next: page.next,
+        })
+    }
+
+    //imports the first page, the sender of the instantiation sends the rest with ImportState
+    pub fn import(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        minimal_donation: Coin,
+        snapshot: StateSnapshot,
+    ) -> Result<Response, ContractError> {
+        if snapshot.minimal_donation != minimal_donation {
+            return Err(invalid_snapshot("minimal_donation differs from the instantiate message"));
+        }
+        if snapshot.start_after.is_some() {
+            return Err(invalid_snapshot("the instantiate message takes the first page"));
+        }
+        validate_header(&snapshot)?;
+        if snapshot.fees_collected.len() > MAX_SNAPSHOT_ENTRIES {
+            return Err(invalid_snapshot(&format!("more than {MAX_SNAPSHOT_ENTRIES} fees_collected entries")));
+        }
+
+        let default = CounterState {
+            counter: snapshot.counter,
+            minimal_donation: snapshot.minimal_donation.clone(),
+            owner: validate_counter(deps.as_ref(), &snapshot.owner, &snapshot.minimal_donation)?,
+            balance: vec![],
+        };
+        save_counter(deps.storage, DEFAULT_COUNTER, &default, env.block.height)?;
+        if let Some(grace_period) = snapshot.grace_period.filter(|seconds| *seconds > 0) {
+            GRACE_PERIOD.save(deps.storage, &grace_period)?;
+        }
+        if let Some(fee) = snapshot.fee.clone() {
+            let fee = fees::validate(deps.api, fee).map_err(|err| invalid_snapshot(&err.to_string()))?;
+            FEE.save(deps.storage, &fee)?;
+        }
+        let mut fees_collected = vec![];
+        add_coins(&mut fees_collected, &snapshot.fees_collected);
+        if !fees_collected.is_empty() {
+            FEES_COLLECTED.save(deps.storage, &fees_collected)?;
+        }
+        import_page(deps, &env, info.sender, snapshot)
+    }
+
+    pub fn import_state(deps: DepsMut, env: Env, info: MessageInfo, snapshot: StateSnapshot) -> Result<Response, ContractError> {
+        let import = IMPORT.may_load(deps.storage)?.ok_or(ContractError::NoImportInProgress {})?;
+        if info.sender != import.importer {
+            return Err(ContractError::Unauthorized { owner: import.importer.to_string() });
+        }
+        validate_header(&snapshot)?;
+        if snapshot.start_after.as_ref() != Some(&import.next) {
+            return Err(invalid_snapshot("the page doesn't follow the one imported last"));
+        }
+        import_page(deps, &env, import.importer, snapshot)
+    }
+
+    //entries are checked against everything imported so far, so lists may span pages
+    fn import_page(mut deps: DepsMut, env: &Env, importer: Addr, snapshot: StateSnapshot) -> Result<Response, ContractError> {
+        let entries = [
+            snapshot.counters.len(),
+            snapshot.tier_thresholds.len(),
+            snapshot.donors.len(),
+            snapshot.attestors.len(),
+            snapshot.verified_donors.len(),
+            #[cfg(feature = "permits")]
+            snapshot.owner_pubkeys.len(),
+            snapshot.audit_log.len(),
+        ];
+        if entries.into_iter().sum::<usize>() > MAX_SNAPSHOT_ENTRIES {
+            return Err(invalid_snapshot(&format!("more than {MAX_SNAPSHOT_ENTRIES} entries in a page")));
+        }
+
+        import_counters(deps.branch(), env, &snapshot)?;
+        import_tiers(deps.branch(), &snapshot)?;
+        import_attestations(deps.branch(), &snapshot)?;
+        #[cfg(feature = "permits")]
+        import_owner_pubkeys(deps.branch(), &snapshot)?;
+        //entries keep their ids and digests, so the log goes on from the exported one
+        let previous = AUDIT_LOG
+            .range(deps.storage, None, None, Order::Descending)
+            .next()
+            .transpose()?
+            .map(|(_, entry)| entry.digest);
+        audit::verify(&snapshot.audit_log, previous.as_ref())
+            .map_err(|id| invalid_snapshot(&format!("audit log entry {id} doesn't match the hash chain")))?;
+        for entry in &snapshot.audit_log {
+            AUDIT_LOG.save(deps.storage, entry.id, entry)?;
+        }
+
+        let complete = snapshot.next.is_none();
+        match snapshot.next {
+            Some(next) => IMPORT.save(deps.storage, &Import { importer, next })?,
+            None => IMPORT.remove(deps.storage),
+        }
+        let resp = Response::new()
+            .add_attribute("action", "import_state")
+            .add_attribute("imported_version", snapshot.contract_info.version)
+            .add_attribute("complete", complete.to_string());
+        Ok(resp)
+    }
+
+    fn invalid_snapshot(reason: &str) -> ContractError {
+        ContractError::InvalidSnapshot { reason: reason.to_owned() }
+    }
+
+    fn parse_version(version: &str) -> Option<Vec<u64>> {
+        version.split('.').map(|part| part.parse().ok()).collect()
+    }
+
+    fn validate_header(snapshot: &StateSnapshot) -> Result<(), ContractError> {
+        //older versions only lack the lists added since, which default to empty
+        if snapshot.version == 0 || snapshot.version > SNAPSHOT_VERSION {
+            return Err(invalid_snapshot(&format!("unsupported snapshot version {}", snapshot.version)));
+        }
+        if snapshot.contract_info.contract != CONTRACT_NAME {
+            return Err(invalid_snapshot(&format!("exported from {}", snapshot.contract_info.contract)));
+        }
+        //importing state of a newer release could drop data this code doesn't know about
+        match (parse_version(&snapshot.contract_info.version), parse_version(CONTRACT_VERSION)) {
+            (Some(snapshot_version), Some(version)) if snapshot_version <= version => Ok(()),
+            _ => Err(invalid_snapshot(&format!("cannot import from version {}", snapshot.contract_info.version))),
+        }
+    }
+
+    fn validate_counter(deps: Deps, owner: &str, minimal_donation: &Coin) -> Result<Addr, ContractError> {
+        let denom = &minimal_donation.denom;
+        if denom.is_empty() || denom.len() > MAX_DENOM_LEN {
+            return Err(invalid_snapshot("minimal_donation denom has invalid length"));
+        }
+
+        deps.api
+            .addr_validate(owner)
+            .map_err(|_| invalid_snapshot("invalid owner address"))
+    }
+
+    //entries of the per counter lists have to belong to an imported counter
+    fn check_counter(deps: Deps, counter_id: &str, list: &str) -> Result<(), ContractError> {
+        if !COUNTERS.has(deps.storage, counter_id) {
+            return Err(invalid_snapshot(&format!("{list} entry of unknown counter {counter_id}")));
+        }
+        Ok(())
+    }
+
+    fn check_new(imported: bool, list: &str) -> Result<(), ContractError> {
+        if imported {
+            return Err(invalid_snapshot(&format!("{list} entry listed twice")));
+        }
+        Ok(())
+    }
+
+    fn validate_address(deps: Deps, address: &str, list: &str) -> Result<Addr, ContractError> {
+        deps.api
+            .addr_validate(address)
+            .map_err(|_| invalid_snapshot(&format!("invalid {list} address")))
+    }
+
+    //imported counters start with empty balances, the funds stay with the exporting contract
+    fn import_counters(deps: DepsMut, env: &Env, snapshot: &StateSnapshot) -> Result<(), ContractError> {
+        for counter in &snapshot.counters {
+            validate_id(&counter.id).map_err(|err| invalid_snapshot(&err.to_string()))?;
+            if COUNTERS.has(deps.storage, &counter.id) {
+                return Err(invalid_snapshot(&format!("counter {} listed twice", counter.id)));
+            }
+            let state = CounterState {
+                counter: counter.counter,
+                minimal_donation: counter.minimal_donation.clone(),
+                owner: validate_counter(deps.as_ref(), &counter.owner, &counter.minimal_donation)?,
+                balance: vec![],
+            };
+            save_counter(deps.storage, &counter.id, &state, env.block.height)?;
+        }
+        Ok(())
+    }
+
+    //donors keep the tier of their last donation, as they would on the exporting contract
+    fn import_tiers(deps: DepsMut, snapshot: &StateSnapshot) -> Result<(), ContractError> {
+        for entry in &snapshot.tier_thresholds {
+            check_counter(deps.as_ref(), &entry.counter_id, "tier_thresholds")?;
+            if entry.denom.is_empty() || entry.denom.len() > MAX_DENOM_LEN || !tiers::valid_thresholds(&entry.thresholds) {
+                return Err(invalid_snapshot(&format!("invalid tier thresholds of counter {}", entry.counter_id)));
+            }
+            let key = (entry.counter_id.as_str(), entry.denom.as_str());
+            check_new(TIER_THRESHOLDS.has(deps.storage, key), "tier_thresholds")?;
+            TIER_THRESHOLDS.save(deps.storage, key, &entry.thresholds)?;
+        }
+
+        for donor in &snapshot.donors {
+            check_counter(deps.as_ref(), &donor.counter_id, "donors")?;
+            let address = validate_address(deps.as_ref(), &donor.address, "donor")?;
+            check_new(DONORS.has(deps.storage, (&donor.counter_id, &address)), "donors")?;
+            let mut donated = vec![];
+            add_coins(&mut donated, &donor.donated);
+            DONORS.save(deps.storage, (&donor.counter_id, &address), &Donor { donated, tier: donor.tier })?;
+            if let Some(tier) = donor.tier {
+                DONORS_BY_TIER.save(deps.storage, (&donor.counter_id, tier.as_str(), &address), &Empty {})?;
+            }
+        }
+        Ok(())
+    }
+
+    fn import_attestations(deps: DepsMut, snapshot: &StateSnapshot) -> Result<(), ContractError> {
+        for attestor in &snapshot.attestors {
+            check_counter(deps.as_ref(), &attestor.counter_id, "attestors")?;
+            if !valid_key(Key::Secp256k1(&attestor.pubkey)) {
+                return Err(invalid_snapshot(&format!("invalid pubkey of attestor {}", attestor.name)));
+            }
+            let key = (attestor.counter_id.as_str(), attestor.name.as_str());
+            check_new(ATTESTORS.has(deps.storage, key), "attestors")?;
+            ATTESTORS.save(deps.storage, key, &attestor.pubkey)?;
+        }
+
+        for donor in &snapshot.verified_donors {
+            check_counter(deps.as_ref(), &donor.counter_id, "verified_donors")?;
+            let address = validate_address(deps.as_ref(), &donor.address, "verified donor")?;
+            check_new(VERIFIED_DONORS.has(deps.storage, (&donor.counter_id, &address)), "verified_donors")?;
+            let verification = Verification {
+                attestor: donor.attestor.clone(),
+                identity: donor.identity.clone(),
+                attestation_hash: donor.attestation_hash.clone(),
+            };
+            VERIFIED_DONORS.save(deps.storage, (&donor.counter_id, &address), &verification)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "permits")]
+    fn import_owner_pubkeys(deps: DepsMut, snapshot: &StateSnapshot) -> Result<(), ContractError> {
+        for entry in &snapshot.owner_pubkeys {
+            check_counter(deps.as_ref(), &entry.counter_id, "owner_pubkeys")?;
+            if !valid_key((&entry.pubkey).into()) {
+                return Err(invalid_snapshot(&format!("invalid owner pubkey of counter {}", entry.counter_id)));
+            }
+            check_new(OWNER_PUBKEYS.has(deps.storage, &entry.counter_id), "owner_pubkeys")?;
+            OWNER_PUBKEYS.save(deps.storage, &entry.counter_id, &entry.pubkey)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    //already on the counters layout, leftovers of older layouts must not overwrite it
+    if !COUNTERS.has(deps.storage, DEFAULT_COUNTER) {
+        let (counter, minimal_donation) = match LEGACY_STATE.may_load(deps.storage)? {
+            Some(state) => (state.counter, state.minimal_donation),
+            None => (
+                LEGACY_COUNTER.load(deps.storage)?,
+                LEGACY_MINIMAL_DONATION.load(deps.storage)?,
+            ),
+        };
+        //older layouts have a single counter, everything the contract holds was donated to it
+        let balance = deps.querier.query_all_balances(&env.contract.address)?;
+        let owner = LEGACY_OWNER.load(deps.storage)?;
 
-    let counter = COUNTER.load(deps.storage)?;
-    let minimal_donation = MINIMAL_DONATION.load(deps.storage)?;
+        COUNTERS.save(
+            deps.storage,
+            DEFAULT_COUNTER,
+            &CounterState {
+                counter,
+                minimal_donation,
+                owner,
+                balance,
+            }
+        )?;
+        LEGACY_STATE.remove(deps.storage);
+        LEGACY_OWNER.remove(deps.storage);
+        LEGACY_COUNTER.remove(deps.storage);
+        LEGACY_MINIMAL_DONATION.remove(deps.storage);
+    }
 
-    STATE.save(
-        deps.storage,
-        &State {
-            counter,
-            minimal_donation
+    //history of counters from releases without it starts now
+    if HISTORY_START.may_load(deps.storage)?.is_none() {
+        HISTORY_START.save(deps.storage, &env.block.height)?;
+        let counters = COUNTERS
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+        for (id, counter) in counters {
+            COUNTER_VALUES.save(deps.storage, &id, &counter.counter, env.block.height)?;
         }
-    )?;
+    }
+
+    let previous = cw2::CONTRACT.may_load(deps.storage)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    let current = cw2::get_contract_version(deps.storage)?;
+    audit::record(deps.storage, &env, None, AuditKind::Migrate, None, &previous, &current)?;
+
+    //done by the contract admin rather than the fee admin, so it is recorded without an actor
+    if let Some(fee) = msg.fee {
+        let previous = FEE.may_load(deps.storage)?;
+        let fee = fees::validate(deps.api, fee)?;
+        FEE.save(deps.storage, &fee)?;
+        audit::record(deps.storage, &env, None, AuditKind::UpdateFee, None, &previous, &fee)?;
+    }
     Ok(Response::new())
 }
 
 pub mod execute {
-    use cosmwasm_std::{StdResult, Response, DepsMut, MessageInfo, Env, BankMsg};
+    use cosmwasm_std::{Addr, Coin, Empty, Env, Order, Response, DepsMut, MessageInfo, BankMsg, Storage};
+    #[cfg(feature = "matching")]
+    use cosmwasm_std::{Decimal, Uint128};
+
+    use super::{save_counter, validate_id};
+    #[cfg(feature = "matching")]
+    use super::{MAX_ACTIVE_SPONSORS, MAX_MATCHING_RATIO};
+    use crate::{audit, fees, tiers};
+    use crate::msg::{AuditKind, FeeMode};
+    use crate::state::{
+        CounterState, PendingDonation, COUNTERS, GRACE_PERIOD, NEXT_DONATION_ID, PENDING_BY_COUNTER,
+        PENDING_DONATIONS,
+    };
+    #[cfg(feature = "matching")]
+    use crate::state::{add_coins, Sponsorship, SPONSORSHIPS};
+    #[cfg(feature = "export")]
+    use crate::msg::StateSnapshot;
+    use crate::{error::ContractError, msg::DEFAULT_COUNTER};
 
-    use crate::{state::{STATE, OWNER}, error::ContractError};
+    pub(crate) fn counter_id(counter_id: Option<String>) -> String {
+        counter_id.unwrap_or_else(|| DEFAULT_COUNTER.to_owned())
+    }
 
-    pub fn donate(deps: DepsMut, info: MessageInfo) -> StdResult<Response> {
-        let mut state = STATE.load(deps.storage)?;
+    pub(crate) fn load_counter(storage: &dyn Storage, id: &str) -> Result<CounterState, ContractError> {
+        COUNTERS
+            .may_load(storage, id)?
+            .ok_or_else(|| ContractError::CounterNotFound { id: id.to_owned() })
+    }
 
-        if info.funds.iter().any(|coin| {
-            coin.denom == state.minimal_donation.denom && coin.amount >= state.minimal_donation.amount
-        }) {
-            state.counter += 1;
-            STATE.save(deps.storage, &state)?;
+    pub fn create_counter(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: String,
+        minimal_donation: Coin,
+        owner: Option<String>,
+    ) -> Result<Response, ContractError> {
+        validate_id(&id)?;
+        if COUNTERS.has(deps.storage, &id) {
+            return Err(ContractError::CounterExists { id });
         }
+        let owner = match owner {
+            Some(owner) => deps.api.addr_validate(&owner)?,
+            None => info.sender,
+        };
+
+        let counter = CounterState {
+            counter: 0,
+            minimal_donation,
+            owner: owner.clone(),
+            balance: vec![],
+        };
+        save_counter(deps.storage, &id, &counter, env.block.height)?;
 
         let resp = Response::new()
+            .add_attribute("action", "create_counter")
+            .add_attribute("counter_id", id)
+            .add_attribute("owner", owner);
+        Ok(resp)
+    }
+
+    pub fn donate(deps: DepsMut, env: Env, info: MessageInfo, counter_id: Option<String>) -> Result<Response, ContractError> {
+        let donor = info.sender.clone();
+        donate_as(deps, env, info, counter_id, donor)
+    }
+
+    //the sender pays, the beneficiary gets the donor stats and tier, and any refund
+    pub fn donate_for(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        counter_id: Option<String>,
+        beneficiary: String,
+    ) -> Result<Response, ContractError> {
+        let beneficiary = deps.api.addr_validate(&beneficiary)?;
+        let resp = donate_as(deps, env, info, counter_id, beneficiary.clone())?
+            .add_attribute("beneficiary", beneficiary);
+        Ok(resp)
+    }
+
+    fn donate_as(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        counter_id: Option<String>,
+        donor: Addr,
+    ) -> Result<Response, ContractError> {
+        let id = self::counter_id(counter_id);
+        let mut counter = load_counter(deps.storage, &id)?;
+
+        let qualifying = info.funds.iter().any(|coin| {
+            coin.denom == counter.minimal_donation.denom && coin.amount >= counter.minimal_donation.amount
+        });
+        if qualifying {
+            counter.counter += 1;
+        }
+        #[cfg(feature = "matching")]
+        let matched = if qualifying { draw_matching(deps.storage, &id, &info.funds)? } else { vec![] };
+        //nothing to match without sponsor pools
+        #[cfg(not(feature = "matching"))]
+        let matched: Vec<(Addr, Coin)> = vec![];
+        let matched_coins: Vec<_> = matched.iter().map(|(_, coin)| coin.clone()).collect();
+
+        let mut resp = Response::new();
+        match GRACE_PERIOD.may_load(deps.storage)? {
+            Some(grace_period) => {
+                //an empty pending donation would only take storage until it is released
+                if info.funds.is_empty() {
+                    return Err(ContractError::Payment(cw_utils::PaymentError::NoFunds {}));
+                }
+                let donation_id = NEXT_DONATION_ID.may_load(deps.storage)?.unwrap_or_default();
+                NEXT_DONATION_ID.save(deps.storage, &(donation_id + 1))?;
+                let release_at = env.block.time.plus_seconds(grace_period);
+                let donation = PendingDonation {
+                    counter_id: id.clone(),
+                    donor: donor.clone(),
+                    funds: info.funds.clone(),
+                    matched,
+                    qualifying,
+                    release_at,
+                };
+                PENDING_DONATIONS.save(deps.storage, donation_id, &donation)?;
+                PENDING_BY_COUNTER.save(deps.storage, (&id, donation_id), &Empty {})?;
+                resp = resp
+                    .add_attribute("donation_id", donation_id.to_string())
+                    .add_attribute("release_at", release_at.seconds().to_string());
+            }
+            None => {
+                let mut received = info.funds.clone();
+                received.extend(matched_coins.iter().cloned());
+                let (received, fee) = fees::charge(deps.storage, FeeMode::Donation, received)?;
+                counter.deposit(&received);
+                resp = resp.add_messages(fee);
+            }
+        }
+        if qualifying {
+            save_counter(deps.storage, &id, &counter, env.block.height)?;
+        } else {
+            COUNTERS.save(deps.storage, &id, &counter)?;
+        }
+        let tier_changed = tiers::record_donation(deps.storage, &id, &donor, &info.funds)?;
+
+        let mut resp = resp
+        .add_events(tier_changed)
         .add_attribute("action", "donate")
+        .add_attribute("counter_id", id)
         .add_attribute("sender", info.sender)
-        .add_attribute("counter", state.counter.to_string());
+        .add_attribute("counter", counter.counter.to_string());
+        if !matched_coins.is_empty() {
+            let matched: Vec<_> = matched_coins.iter().map(Coin::to_string).collect();
+            resp = resp.add_attribute("matched", matched.join(","));
+        }
         Ok(resp)
     }
-    pub fn withdraw(deps: DepsMut, info: MessageInfo, env: Env) -> Result<Response, ContractError> {
-        let owner = OWNER.load(deps.storage)?;
-        if info.sender != owner {
-            return Err(ContractError::Unauthorized { owner: owner.to_string() });
+
+    pub fn cancel_donation(deps: DepsMut, env: Env, info: MessageInfo, donation_id: u64) -> Result<Response, ContractError> {
+        let donation = PENDING_DONATIONS
+            .may_load(deps.storage, donation_id)?
+            .ok_or(ContractError::DonationNotFound { id: donation_id })?;
+        if info.sender != donation.donor {
+            return Err(ContractError::Unauthorized { owner: donation.donor.to_string() });
         }
-        //queries contract state and token value
-        let balance = deps.querier.query_all_balances(&env.contract.address)?;
-        let bank_msg = BankMsg::Send { to_address: info.sender.to_string(), amount: balance };
+        if env.block.time >= donation.release_at {
+            return Err(ContractError::GracePeriodEnded { id: donation_id });
+        }
+        let id = donation.counter_id;
+        PENDING_DONATIONS.remove(deps.storage, donation_id);
+        PENDING_BY_COUNTER.remove(deps.storage, (&id, donation_id));
+
+        let mut counter = load_counter(deps.storage, &id)?;
+        if donation.qualifying {
+            counter.counter -= 1;
+            save_counter(deps.storage, &id, &counter, env.block.height)?;
+        }
+        //closed pools get the match back too, the owner can close them again
+        #[cfg(feature = "matching")]
+        for (sponsor, coin) in donation.matched {
+            let mut sponsorship = SPONSORSHIPS.load(deps.storage, (&id, &sponsor))?;
+            sponsorship.remaining.amount += coin.amount;
+            sponsorship.matched -= coin.amount;
+            SPONSORSHIPS.save(deps.storage, (&id, &sponsor), &sponsorship)?;
+        }
+        tiers::revoke_donation(deps.storage, &id, &donation.donor, &donation.funds)?;
+
+        let mut resp = Response::new();
+        if !donation.funds.is_empty() {
+            resp = resp.add_message(BankMsg::Send { to_address: info.sender.to_string(), amount: donation.funds });
+        }
+
+        let resp = resp
+            .add_attribute("action", "cancel_donation")
+            .add_attribute("donation_id", donation_id.to_string())
+            .add_attribute("counter_id", id)
+            .add_attribute("sender", info.sender)
+            .add_attribute("counter", counter.counter.to_string());
+        Ok(resp)
+    }
+
+    //moves donations whose grace period has passed into the counter balance, returns the fee
+    //taken from them
+    pub(crate) fn settle_donations(
+        storage: &mut dyn Storage,
+        env: &Env,
+        id: &str,
+        counter: &mut CounterState,
+    ) -> Result<Option<BankMsg>, ContractError> {
+        let released = release_donations(storage, env, id)?;
+        let (released, fee) = fees::charge(storage, FeeMode::Donation, released)?;
+        counter.deposit(&released);
+        Ok(fee)
+    }
+
+    //takes donations whose grace period has passed out of the pending ones, returns their
+    //funds with the matches; the grace period never changes, so donations are released in id
+    //order and the first one still pending ends the scan
+    fn release_donations(storage: &mut dyn Storage, env: &Env, id: &str) -> Result<Vec<Coin>, ContractError> {
+        let mut due = vec![];
+        for donation_id in PENDING_BY_COUNTER.prefix(id).keys(storage, None, None, Order::Ascending) {
+            let donation_id = donation_id?;
+            let donation = PENDING_DONATIONS.load(storage, donation_id)?;
+            if env.block.time < donation.release_at {
+                break;
+            }
+            due.push((donation_id, donation));
+        }
+
+        let mut released = vec![];
+        for (donation_id, donation) in due {
+            released.extend(donation.funds);
+            released.extend(donation.matched.into_iter().map(|(_, coin)| coin));
+            PENDING_DONATIONS.remove(storage, donation_id);
+            PENDING_BY_COUNTER.remove(storage, (id, donation_id));
+        }
+        Ok(released)
+    }
+
+    //takes every sponsor's match for a qualifying donation out of the pool
+    #[cfg(feature = "matching")]
+    fn draw_matching(storage: &mut dyn Storage, id: &str, funds: &[Coin]) -> Result<Vec<(Addr, Coin)>, ContractError> {
+        let sponsorships = SPONSORSHIPS
+            .prefix(id)
+            .range(storage, None, None, Order::Ascending)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut matched = vec![];
+        for (sponsor, mut sponsorship) in sponsorships {
+            if sponsorship.remaining.amount.is_zero() {
+                continue;
+            }
+            let donated: Uint128 = funds
+                .iter()
+                .filter(|coin| coin.denom == sponsorship.remaining.denom)
+                .map(|coin| coin.amount)
+                .sum();
+            //a match too large for Uint128 is above any cap anyway
+            let amount = donated
+                .checked_mul_floor(sponsorship.ratio)
+                .unwrap_or(sponsorship.cap)
+                .min(sponsorship.cap)
+                .min(sponsorship.remaining.amount);
+            if amount.is_zero() {
+                continue;
+            }
+            sponsorship.remaining.amount -= amount;
+            sponsorship.matched += amount;
+            SPONSORSHIPS.save(storage, (id, &sponsor), &sponsorship)?;
+            matched.push((sponsor, Coin::new(amount.u128(), sponsorship.remaining.denom)));
+        }
+        Ok(matched)
+    }
+
+    #[cfg(feature = "matching")]
+    pub fn fund_matching(
+        deps: DepsMut,
+        info: MessageInfo,
+        counter_id: Option<String>,
+        ratio: Decimal,
+        cap: Uint128,
+    ) -> Result<Response, ContractError> {
+        let id = self::counter_id(counter_id);
+        load_counter(deps.storage, &id)?;
+        if ratio.is_zero() || ratio > MAX_MATCHING_RATIO {
+            return Err(ContractError::InvalidMatchingRatio { max: MAX_MATCHING_RATIO });
+        }
+        let deposit = cw_utils::one_coin(&info)?;
+
+        let key = (id.as_str(), &info.sender);
+        let existing = SPONSORSHIPS.may_load(deps.storage, key)?;
+        //a sponsor with nothing remaining takes a slot again; close_matching frees them all
+        if existing.as_ref().is_none_or(|sponsorship| sponsorship.remaining.amount.is_zero()) {
+            let mut active = 0;
+            for sponsorship in SPONSORSHIPS.prefix(&id).range(deps.storage, None, None, Order::Ascending) {
+                if !sponsorship?.1.remaining.amount.is_zero() {
+                    active += 1;
+                }
+            }
+            if active >= MAX_ACTIVE_SPONSORS {
+                return Err(ContractError::TooManySponsors { max: MAX_ACTIVE_SPONSORS });
+            }
+        }
+        let sponsorship = match existing {
+            Some(sponsorship) if sponsorship.remaining.denom != deposit.denom => {
+                return Err(ContractError::MatchingDenomMismatch { denom: sponsorship.remaining.denom });
+            }
+            Some(mut sponsorship) => {
+                sponsorship.remaining.amount += deposit.amount;
+                Sponsorship { ratio, cap, ..sponsorship }
+            }
+            None => Sponsorship { ratio, cap, remaining: deposit.clone(), matched: Uint128::zero() },
+        };
+        SPONSORSHIPS.save(deps.storage, key, &sponsorship)?;
 
         let resp = Response::new()
-            .add_message(bank_msg)
+            .add_attribute("action", "fund_matching")
+            .add_attribute("counter_id", id)
+            .add_attribute("sponsor", info.sender)
+            .add_attribute("deposit", deposit.to_string());
+        Ok(resp)
+    }
+
+    #[cfg(feature = "matching")]
+    pub fn close_matching(deps: DepsMut, env: Env, info: MessageInfo, counter_id: Option<String>) -> Result<Response, ContractError> {
+        let id = self::counter_id(counter_id);
+        let counter = load_counter(deps.storage, &id)?;
+        if info.sender != counter.owner {
+            return Err(ContractError::Unauthorized { owner: counter.owner.to_string() });
+        }
+
+        let sponsorships = SPONSORSHIPS
+            .prefix(&id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut resp = Response::new();
+        let mut refunded = vec![];
+        //sponsorships are kept with nothing remaining, so the matched totals stay queryable
+        for (sponsor, mut sponsorship) in sponsorships {
+            if sponsorship.remaining.amount.is_zero() {
+                continue;
+            }
+            let refund = sponsorship.remaining.clone();
+            add_coins(&mut refunded, std::slice::from_ref(&refund));
+            sponsorship.remaining.amount = Uint128::zero();
+            SPONSORSHIPS.save(deps.storage, (&id, &sponsor), &sponsorship)?;
+            resp = resp.add_message(BankMsg::Send { to_address: sponsor.to_string(), amount: vec![refund] });
+        }
+        //remaining matching capacity before and after
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::CloseMatching, Some(id.clone()), &refunded, &Vec::<Coin>::new())?;
+
+        let resp = resp
+            .add_attribute("action", "close_matching")
+            .add_attribute("counter_id", id)
+            .add_attribute("sender", info.sender);
+        Ok(resp)
+    }
+
+    pub fn withdraw(deps: DepsMut, env: Env, info: MessageInfo, counter_id: Option<String>) -> Result<Response, ContractError> {
+        let id = self::counter_id(counter_id);
+        let mut counter = load_counter(deps.storage, &id)?;
+        if info.sender != counter.owner {
+            return Err(ContractError::Unauthorized { owner: counter.owner.to_string() });
+        }
+        let donation_fee = settle_donations(deps.storage, &env, &id, &mut counter)?;
+        let balance = std::mem::take(&mut counter.balance);
+        COUNTERS.save(deps.storage, &id, &counter)?;
+        //counter balance before and after
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::Withdraw, Some(id.clone()), &balance, &counter.balance)?;
+        let (balance, withdraw_fee) = fees::charge(deps.storage, FeeMode::Withdraw, balance)?;
+
+        let mut resp = Response::new().add_messages(donation_fee).add_messages(withdraw_fee);
+        //bank rejects sends without coins, nothing to do on an empty counter
+        if !balance.is_empty() {
+            resp = resp.add_message(BankMsg::Send { to_address: info.sender.to_string(), amount: balance });
+        }
+
+        let resp = resp
             .add_attribute("action", "withdraw")
+            .add_attribute("counter_id", id)
             .add_attribute("sender", info.sender.to_string());
 
         Ok(resp)
 
     }
+
+    #[cfg(feature = "export")]
+    pub fn import_state(deps: DepsMut, env: Env, info: MessageInfo, snapshot: StateSnapshot) -> Result<Response, ContractError> {
+        super::snapshot::import_state(deps, env, info, snapshot)
+    }
 }
 
 pub mod query {
-    use crate::msg::ValueResp;
-    use crate::state::STATE;
-    use cosmwasm_std::{Deps, StdResult};
+    use crate::msg::{ConfigResp, CounterResp, CountersResp, ValueResp};
+    #[cfg(feature = "matching")]
+    use crate::msg::{MatchingResp, SponsorResp};
+    #[cfg(feature = "export")]
+    use crate::msg::{SnapshotCursor, StateSnapshot};
+    use crate::state::{COUNTERS, COUNTER_VALUES, HISTORY_START};
+    #[cfg(feature = "matching")]
+    use crate::state::SPONSORSHIPS;
+    #[cfg(feature = "matching")]
+    use cosmwasm_std::Coin;
+    use cosmwasm_std::{Deps, Order, StdError, StdResult};
+    use cw_storage_plus::Bound;
+
+    use super::execute::counter_id;
 
-    pub fn value(deps: Deps) -> StdResult<ValueResp> {
-        let value = STATE.load(deps.storage)?.counter;
+    const DEFAULT_LIMIT: u32 = 10;
+    const MAX_LIMIT: u32 = 30;
+
+    //number of entries a paginated query returns
+    pub(crate) fn page_limit(limit: Option<u32>) -> usize {
+        limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize
+    }
+
+    pub fn value(deps: Deps, counter_id: Option<String>) -> StdResult<ValueResp> {
+        let value = COUNTERS.load(deps.storage, &self::counter_id(counter_id))?.counter;
         Ok(ValueResp { value })
     }
+
+    pub fn value_at_height(deps: Deps, counter_id: Option<String>, height: u64) -> StdResult<ValueResp> {
+        let id = self::counter_id(counter_id);
+        COUNTERS.load(deps.storage, &id)?;
+        let start = HISTORY_START.load(deps.storage)?;
+        if height < start {
+            return Err(StdError::generic_err(format!("counter history starts at height {start}")));
+        }
+        //the changelog keeps values from before the changes made at a height
+        let value = COUNTER_VALUES
+            .may_load_at_height(deps.storage, &id, height + 1)?
+            .unwrap_or_default();
+        Ok(ValueResp { value })
+    }
+
+    pub fn config(deps: Deps, counter_id: Option<String>) -> StdResult<ConfigResp> {
+        let counter = COUNTERS.load(deps.storage, &self::counter_id(counter_id))?;
+        Ok(ConfigResp { owner: counter.owner, minimal_donation: counter.minimal_donation })
+    }
+
+    pub fn counters(deps: Deps, start_after: Option<String>, limit: Option<u32>) -> StdResult<CountersResp> {
+        let limit = page_limit(limit);
+        let start = start_after.as_deref().map(Bound::exclusive);
+
+        let counters = COUNTERS
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .map(|item| {
+                let (id, counter) = item?;
+                Ok(CounterResp {
+                    id,
+                    owner: counter.owner,
+                    minimal_donation: counter.minimal_donation,
+                    value: counter.counter,
+                    balance: counter.balance,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        Ok(CountersResp { counters })
+    }
+
+    #[cfg(feature = "matching")]
+    pub fn matching(deps: Deps, counter_id: Option<String>) -> StdResult<MatchingResp> {
+        let id = self::counter_id(counter_id);
+        COUNTERS.load(deps.storage, &id)?;
+
+        let sponsors: Vec<_> = SPONSORSHIPS
+            .prefix(&id)
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| {
+                let (sponsor, sponsorship) = item?;
+                Ok(SponsorResp {
+                    sponsor,
+                    ratio: sponsorship.ratio,
+                    cap: sponsorship.cap,
+                    matched: Coin::new(sponsorship.matched.u128(), &sponsorship.remaining.denom),
+                    remaining: sponsorship.remaining,
+                })
+            })
+            .collect::<StdResult<_>>()?;
+
+        let mut remaining: Vec<Coin> = vec![];
+        for sponsor in sponsors.iter().filter(|sponsor| !sponsor.remaining.amount.is_zero()) {
+            match remaining.iter_mut().find(|coin| coin.denom == sponsor.remaining.denom) {
+                Some(coin) => coin.amount += sponsor.remaining.amount,
+                None => remaining.push(sponsor.remaining.clone()),
+            }
+        }
+        remaining.sort_by(|a, b| a.denom.cmp(&b.denom));
+
+        Ok(MatchingResp { sponsors, remaining })
+    }
+
+    #[cfg(feature = "export")]
+    pub fn export_state(deps: Deps, start_after: Option<SnapshotCursor>, limit: Option<u32>) -> StdResult<StateSnapshot> {
+        super::snapshot::export(deps, start_after, limit)
+    }
 }
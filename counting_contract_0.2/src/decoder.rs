@@ -0,0 +1,356 @@
+#[cfg(feature = "rounds")]
+use cosmwasm_std::Uint128;
+use cosmwasm_std::{from_json, Addr, Binary, Coin, HexBinary, StdError, StdResult};
+use cw2::ContractVersion;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "permits")]
+use crate::msg::OwnerPubkey;
+use crate::msg::{AuditEntry, TierThresholds};
+use crate::state::{
+    CounterState, Donor, Fee, PendingDonation, State, Verification, ATTESTORS, AUDIT_LOG, COUNTERS, COUNTER_VALUES,
+    COUNTER_VALUES_NAMESPACE, DONORS, DONORS_BY_TIER, FEE, FEES_COLLECTED, GRACE_PERIOD, HISTORY_START, LEGACY_COUNTER,
+    LEGACY_MINIMAL_DONATION, LEGACY_OWNER, LEGACY_STATE, NEXT_DONATION_ID, PENDING_BY_COUNTER, PENDING_DONATIONS,
+    TIER_THRESHOLDS, VERIFIED_DONORS,
+};
+#[cfg(feature = "export")]
+use crate::state::{Import, IMPORT};
+#[cfg(feature = "matching")]
+use crate::state::{Sponsorship, SPONSORSHIPS};
+#[cfg(feature = "permits")]
+use crate::state::{OWNER_PUBKEYS, PERMIT_NONCES};
+#[cfg(feature = "rounds")]
+use crate::state::{Project, Round, CONTRIBUTIONS, PROJECTS, ROUNDS};
+
+//decodes raw key/value dumps of the contract storage, as returned by
+//`wasmd query wasm contract-state all`, using the layouts of every released version
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case", tag = "item", content = "value")]
+pub enum KnownValue {
+    Counter(u64),
+    MinimalDonation(Coin),
+    Owner(Addr),
+    State(State),
+    NamedCounter { id: String, state: CounterState },
+    //the key holds the counter id and sponsor
+    #[cfg(feature = "matching")]
+    Sponsorship(Sponsorship),
+    //keys of rounds and projects hold their ids, contribution keys also the donor
+    #[cfg(feature = "rounds")]
+    Round(Round),
+    #[cfg(feature = "rounds")]
+    Project(Project),
+    #[cfg(feature = "rounds")]
+    Contribution(Uint128),
+    TierThresholds(TierThresholds),
+    Donor(Donor),
+    //the whole entry is in the key
+    DonorTierIndex,
+    GracePeriod(u64),
+    NextDonationId(u64),
+    PendingDonation(PendingDonation),
+    PendingByCounter,
+    Fee(Fee),
+    FeesCollected(Vec<Coin>),
+    AuditEntry(AuditEntry),
+    CounterValue(u64),
+    //value before a change, keyed by counter id and height
+    CounterValueChange { old: Option<u64> },
+    HistoryStart(u64),
+    //public key, the key holds the counter id and attestor name
+    Attestor(Binary),
+    Verification(Verification),
+    #[cfg(feature = "permits")]
+    OwnerPubkey(OwnerPubkey),
+    //the key holds the counter id and nonce
+    #[cfg(feature = "permits")]
+    PermitNonce,
+    #[cfg(feature = "export")]
+    Import(Import),
+    ContractInfo(ContractVersion),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStatus {
+    Current,
+    //written by an older version and no longer read
+    Legacy,
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    #[serde(rename = "0.1")]
+    V0_1,
+    #[serde(rename = "0.2")]
+    V0_2,
+    //0.2 with named counters
+    Counters,
+    Unknown,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DecodedEntry {
+    pub key: String,
+    pub key_hex: HexBinary,
+    pub status: KeyStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<KnownValue>,
+    //kept for unknown keys and values which failed to decode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Binary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Dump {
+    pub layout: Layout,
+    pub entries: Vec<DecodedEntry>,
+}
+
+impl Dump {
+    pub fn flagged(&self) -> impl Iterator<Item = &DecodedEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.status != KeyStatus::Current || entry.error.is_some())
+    }
+}
+
+//changelog entry of a snapshot map
+#[derive(Deserialize)]
+struct ChangeSet {
+    old: Option<u64>,
+}
+
+//maps store entries under their namespace prefixed with its length
+fn map_prefix(namespace: &[u8]) -> Vec<u8> {
+    let mut prefix = (namespace.len() as u16).to_be_bytes().to_vec();
+    prefix.extend_from_slice(namespace);
+    prefix
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Item {
+    Counter,
+    MinimalDonation,
+    Owner,
+    State,
+    NamedCounter,
+    #[cfg(feature = "matching")]
+    Sponsorship,
+    #[cfg(feature = "rounds")]
+    Round,
+    #[cfg(feature = "rounds")]
+    Project,
+    #[cfg(feature = "rounds")]
+    Contribution,
+    TierThresholds,
+    Donor,
+    DonorTierIndex,
+    GracePeriod,
+    NextDonationId,
+    PendingDonation,
+    PendingByCounter,
+    Fee,
+    FeesCollected,
+    AuditEntry,
+    CounterValue,
+    CounterValueChange,
+    HistoryStart,
+    Attestor,
+    Verification,
+    #[cfg(feature = "permits")]
+    OwnerPubkey,
+    #[cfg(feature = "permits")]
+    PermitNonce,
+    #[cfg(feature = "export")]
+    Import,
+    ContractInfo,
+}
+
+impl Item {
+    fn from_key(key: &[u8]) -> Option<Self> {
+        let maps = [
+            (COUNTERS.namespace(), Item::NamedCounter),
+            #[cfg(feature = "matching")]
+            (SPONSORSHIPS.namespace(), Item::Sponsorship),
+            #[cfg(feature = "rounds")]
+            (ROUNDS.namespace(), Item::Round),
+            #[cfg(feature = "rounds")]
+            (PROJECTS.namespace(), Item::Project),
+            #[cfg(feature = "rounds")]
+            (CONTRIBUTIONS.namespace(), Item::Contribution),
+            (TIER_THRESHOLDS.namespace(), Item::TierThresholds),
+            (DONORS.namespace(), Item::Donor),
+            (DONORS_BY_TIER.namespace(), Item::DonorTierIndex),
+            (PENDING_DONATIONS.namespace(), Item::PendingDonation),
+            (PENDING_BY_COUNTER.namespace(), Item::PendingByCounter),
+            (AUDIT_LOG.namespace(), Item::AuditEntry),
+            (COUNTER_VALUES_NAMESPACE.as_bytes(), Item::CounterValue),
+            (COUNTER_VALUES.changelog().namespace(), Item::CounterValueChange),
+            (ATTESTORS.namespace(), Item::Attestor),
+            (VERIFIED_DONORS.namespace(), Item::Verification),
+            #[cfg(feature = "permits")]
+            (OWNER_PUBKEYS.namespace(), Item::OwnerPubkey),
+            #[cfg(feature = "permits")]
+            (PERMIT_NONCES.namespace(), Item::PermitNonce),
+        ];
+        if let Some((_, item)) = maps
+            .into_iter()
+            .find(|(namespace, _)| key.starts_with(&map_prefix(namespace)))
+        {
+            return Some(item);
+        }
+        [
+            (LEGACY_COUNTER.as_slice(), Item::Counter),
+            (LEGACY_MINIMAL_DONATION.as_slice(), Item::MinimalDonation),
+            (LEGACY_OWNER.as_slice(), Item::Owner),
+            (LEGACY_STATE.as_slice(), Item::State),
+            (GRACE_PERIOD.as_slice(), Item::GracePeriod),
+            (NEXT_DONATION_ID.as_slice(), Item::NextDonationId),
+            (FEE.as_slice(), Item::Fee),
+            (FEES_COLLECTED.as_slice(), Item::FeesCollected),
+            (HISTORY_START.as_slice(), Item::HistoryStart),
+            #[cfg(feature = "export")]
+            (IMPORT.as_slice(), Item::Import),
+            (cw2::CONTRACT.as_slice(), Item::ContractInfo),
+        ]
+        .into_iter()
+        .find(|(item_key, _)| *item_key == key)
+        .map(|(_, item)| item)
+    }
+
+    fn decode(self, key: &[u8], value: &[u8]) -> StdResult<KnownValue> {
+        Ok(match self {
+            Item::Counter => KnownValue::Counter(from_json(value)?),
+            Item::MinimalDonation => KnownValue::MinimalDonation(from_json(value)?),
+            Item::Owner => KnownValue::Owner(from_json(value)?),
+            Item::State => KnownValue::State(from_json(value)?),
+            Item::NamedCounter => KnownValue::NamedCounter {
+                id: String::from_utf8(key[map_prefix(COUNTERS.namespace()).len()..].to_vec())?,
+                state: from_json(value)?,
+            },
+            #[cfg(feature = "matching")]
+            Item::Sponsorship => KnownValue::Sponsorship(from_json(value)?),
+            #[cfg(feature = "rounds")]
+            Item::Round => KnownValue::Round(from_json(value)?),
+            #[cfg(feature = "rounds")]
+            Item::Project => KnownValue::Project(from_json(value)?),
+            #[cfg(feature = "rounds")]
+            Item::Contribution => KnownValue::Contribution(from_json(value)?),
+            Item::TierThresholds => KnownValue::TierThresholds(from_json(value)?),
+            Item::Donor => KnownValue::Donor(from_json(value)?),
+            Item::DonorTierIndex => KnownValue::DonorTierIndex,
+            Item::GracePeriod => KnownValue::GracePeriod(from_json(value)?),
+            Item::NextDonationId => KnownValue::NextDonationId(from_json(value)?),
+            Item::PendingDonation => KnownValue::PendingDonation(from_json(value)?),
+            Item::PendingByCounter => KnownValue::PendingByCounter,
+            Item::Fee => KnownValue::Fee(from_json(value)?),
+            Item::FeesCollected => KnownValue::FeesCollected(from_json(value)?),
+            Item::AuditEntry => KnownValue::AuditEntry(from_json(value)?),
+            Item::CounterValue => KnownValue::CounterValue(from_json(value)?),
+            Item::CounterValueChange => {
+                let change: ChangeSet = from_json(value)?;
+                KnownValue::CounterValueChange { old: change.old }
+            }
+            Item::HistoryStart => KnownValue::HistoryStart(from_json(value)?),
+            Item::Attestor => KnownValue::Attestor(from_json(value)?),
+            Item::Verification => KnownValue::Verification(from_json(value)?),
+            #[cfg(feature = "permits")]
+            Item::OwnerPubkey => KnownValue::OwnerPubkey(from_json(value)?),
+            #[cfg(feature = "permits")]
+            Item::PermitNonce => KnownValue::PermitNonce,
+            #[cfg(feature = "export")]
+            Item::Import => KnownValue::Import(from_json(value)?),
+            Item::ContractInfo => KnownValue::ContractInfo(from_json(value)?),
+        })
+    }
+
+    fn status(self, layout: Layout) -> KeyStatus {
+        match (self, layout) {
+            (Item::Counter | Item::MinimalDonation, Layout::V0_2) => KeyStatus::Legacy,
+            (Item::Counter | Item::MinimalDonation | Item::Owner | Item::State, Layout::Counters) => {
+                KeyStatus::Legacy
+            }
+            _ => KeyStatus::Current,
+        }
+    }
+}
+
+pub fn decode(records: &[(Vec<u8>, Vec<u8>)]) -> Dump {
+    let has = |item| records.iter().any(|(key, _)| Item::from_key(key) == Some(item));
+    let layout = if has(Item::NamedCounter) {
+        Layout::Counters
+    } else if has(Item::State) {
+        Layout::V0_2
+    } else if has(Item::Counter) {
+        Layout::V0_1
+    } else {
+        Layout::Unknown
+    };
+
+    let entries = records
+        .iter()
+        .map(|(key, value)| {
+            let mut entry = DecodedEntry {
+                key: String::from_utf8_lossy(key).into_owned(),
+                key_hex: HexBinary::from(key.as_slice()),
+                status: KeyStatus::Unknown,
+                value: None,
+                raw: None,
+                error: None,
+            };
+            match Item::from_key(key) {
+                Some(item) => {
+                    entry.status = item.status(layout);
+                    match item.decode(key, value) {
+                        Ok(value) => entry.value = Some(value),
+                        Err(err) => {
+                            entry.raw = Some(Binary::from(value.as_slice()));
+                            entry.error = Some(err.to_string());
+                        }
+                    }
+                }
+                None => entry.raw = Some(Binary::from(value.as_slice())),
+            }
+            entry
+        })
+        .collect();
+
+    Dump { layout, entries }
+}
+
+#[derive(Deserialize)]
+struct Model {
+    key: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DumpFile {
+    Query { models: Vec<Model> },
+    Models(Vec<Model>),
+}
+
+//reads the json of a state query, either the full response or just its models
+pub fn parse_dump(data: &[u8]) -> StdResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    let models = match serde_json::from_slice(data)
+        .map_err(|err| StdError::parse_err("state dump", err))?
+    {
+        DumpFile::Query { models } | DumpFile::Models(models) => models,
+    };
+
+    models
+        .into_iter()
+        .map(|model| {
+            let key = HexBinary::from_hex(&model.key)?;
+            let value = Binary::from_base64(&model.value)?;
+            Ok((key.into(), value.into()))
+        })
+        .collect()
+}
@@ -0,0 +1,95 @@
+use cosmwasm_std::{Api, BankMsg, Coin, StdResult, Storage};
+
+use crate::error::ContractError;
+use crate::msg::{FeeConfig, FeeMode};
+use crate::state::{add_coins, Fee, FEE, FEES_COLLECTED};
+
+const MAX_BPS: u16 = 10_000;
+
+pub fn validate(api: &dyn Api, fee: FeeConfig) -> Result<Fee, ContractError> {
+    if fee.bps > MAX_BPS {
+        return Err(ContractError::InvalidFeeBps { bps: fee.bps });
+    }
+    Ok(Fee {
+        bps: fee.bps,
+        collector: api.addr_validate(&fee.collector)?,
+        admin: api.addr_validate(&fee.admin)?,
+        mode: fee.mode,
+    })
+}
+
+//splits the fee off coins a counter receives when it is charged in mode, returns what is
+//left for the counter and the transfer to the collector
+pub fn charge(storage: &mut dyn Storage, mode: FeeMode, coins: Vec<Coin>) -> StdResult<(Vec<Coin>, Option<BankMsg>)> {
+    let fee = match FEE.may_load(storage)? {
+        Some(fee) if fee.mode == mode => fee,
+        _ => return Ok((coins, None)),
+    };
+
+    let mut net = vec![];
+    let mut charged = vec![];
+    for mut coin in coins {
+        //rounded down, small donations may pass free
+        let amount = coin.amount.multiply_ratio(fee.bps, MAX_BPS);
+        if !amount.is_zero() {
+            coin.amount -= amount;
+            charged.push(Coin::new(amount.u128(), &coin.denom));
+        }
+        if !coin.amount.is_zero() {
+            net.push(coin);
+        }
+    }
+    if charged.is_empty() {
+        return Ok((net, None));
+    }
+
+    let mut collected = FEES_COLLECTED.may_load(storage)?.unwrap_or_default();
+    add_coins(&mut collected, &charged);
+    FEES_COLLECTED.save(storage, &collected)?;
+    let transfer = BankMsg::Send { to_address: fee.collector.into_string(), amount: charged };
+    Ok((net, Some(transfer)))
+}
+
+pub mod execute {
+    use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+
+    use crate::audit;
+    use crate::error::ContractError;
+    use crate::msg::{AuditKind, FeeConfig};
+    use crate::state::FEE;
+
+    pub fn update_fee(deps: DepsMut, env: Env, info: MessageInfo, fee: FeeConfig) -> Result<Response, ContractError> {
+        let current = FEE.may_load(deps.storage)?.ok_or(ContractError::FeeNotConfigured {})?;
+        if info.sender != current.admin {
+            return Err(ContractError::Unauthorized { owner: current.admin.to_string() });
+        }
+        let fee = super::validate(deps.api, fee)?;
+        FEE.save(deps.storage, &fee)?;
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::UpdateFee, None, &current, &fee)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "update_fee")
+            .add_attribute("sender", info.sender)
+            .add_attribute("bps", fee.bps.to_string())
+            .add_attribute("collector", fee.collector);
+        Ok(resp)
+    }
+}
+
+pub mod query {
+    use cosmwasm_std::{Deps, StdResult};
+
+    use crate::msg::{FeeConfig, FeeReportResp};
+    use crate::state::{FEE, FEES_COLLECTED};
+
+    pub fn fee_report(deps: Deps) -> StdResult<FeeReportResp> {
+        let fee = FEE.may_load(deps.storage)?.map(|fee| FeeConfig {
+            bps: fee.bps,
+            collector: fee.collector.into_string(),
+            admin: fee.admin.into_string(),
+            mode: fee.mode,
+        });
+        let collected = FEES_COLLECTED.may_load(deps.storage)?.unwrap_or_default();
+        Ok(FeeReportResp { fee, collected })
+    }
+}
@@ -1,35 +1,47 @@
 use crate::msg::QueryMsg;
 use cosmwasm_std::{
-    to_binary, Empty, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
 };
 
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 
 use error::ContractError;
-use msg::{ExecuteMsg, InstantiateMsg};
+use msg::{ExecuteMsg, InstantiateMsg, MigrateMsg};
 
+mod attestations;
+mod audit;
 mod contract;
-pub mod msg;
+mod fees;
+#[cfg(feature = "permits")]
+mod permits;
+#[cfg(feature = "rounds")]
+mod rounds;
+mod signatures;
+mod tiers;
+#[cfg(any(test, feature = "decoder"))]
+pub mod decoder;
+pub use counting_contract_interface::{error, msg};
 mod state;
-pub mod error;
 #[cfg(any(test, feature = "tests"))]
 pub mod multitest;
+#[cfg(feature = "vm")]
+pub mod vm;
 
 //hides entry point when library feature is enabled
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> Result<Response, StdError> {
-    contract::instantiate(deps, info, msg)
+) -> Result<Response, ContractError> {
+    contract::instantiate(deps, env, info, msg)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn migrate(deps: DepsMut, _env: Env, _msg: Empty) -> StdResult<Response> {
-    contract::migrate(deps)
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    contract::migrate(deps, env, msg)
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -41,9 +53,70 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     use msg::ExecuteMsg::*;
 
+    //a contract importing a snapshot takes nothing but its remaining pages
+    #[cfg(feature = "export")]
+    if !matches!(msg, ImportState { .. }) && state::IMPORT.may_load(deps.storage)?.is_some() {
+        return Err(ContractError::ImportInProgress {});
+    }
+
     match msg {
-        Donate {} => contract::execute::donate(deps, info).map_err(ContractError::Std),
-        Withdraw {} => contract::execute::withdraw(deps, info, env),
+        CreateCounter { id, minimal_donation, owner } => {
+            contract::execute::create_counter(deps, env, info, id, minimal_donation, owner)
+        }
+        Donate { counter_id } => contract::execute::donate(deps, env, info, counter_id),
+        DonateFor { counter_id, beneficiary } => {
+            contract::execute::donate_for(deps, env, info, counter_id, beneficiary)
+        }
+        DonateAttested { counter_id, attestation } => {
+            attestations::execute::donate_attested(deps, env, info, counter_id, attestation)
+        }
+        CancelDonation { id } => contract::execute::cancel_donation(deps, env, info, id),
+        Withdraw { counter_id } => contract::execute::withdraw(deps, env, info, counter_id),
+        #[cfg(feature = "matching")]
+        FundMatching { counter_id, ratio, cap } => {
+            contract::execute::fund_matching(deps, info, counter_id, ratio, cap)
+        }
+        #[cfg(feature = "matching")]
+        CloseMatching { counter_id } => {
+            contract::execute::close_matching(deps, env, info, counter_id)
+        }
+        #[cfg(feature = "rounds")]
+        CreateRound { id, denom, end_time } => {
+            rounds::execute::create_round(deps, env, info, id, denom, end_time)
+        }
+        #[cfg(feature = "rounds")]
+        RegisterProject { round_id, project_id, owner } => {
+            rounds::execute::register_project(deps, env, info, round_id, project_id, owner)
+        }
+        #[cfg(feature = "rounds")]
+        FundRound { round_id } => rounds::execute::fund_round(deps, env, info, round_id),
+        #[cfg(feature = "rounds")]
+        DonateToProject { round_id, project_id } => {
+            rounds::execute::donate_to_project(deps, env, info, round_id, project_id)
+        }
+        #[cfg(feature = "rounds")]
+        FinalizeRound { round_id } => rounds::execute::finalize_round(deps, env, info, round_id),
+        #[cfg(feature = "rounds")]
+        WithdrawProject { round_id, project_id } => {
+            rounds::execute::withdraw_project(deps, env, info, round_id, project_id)
+        }
+        SetTierThresholds { counter_id, denom, thresholds } => {
+            tiers::execute::set_tier_thresholds(deps, env, info, counter_id, denom, thresholds)
+        }
+        SetAttestor { counter_id, name, pubkey } => {
+            attestations::execute::set_attestor(deps, env, info, counter_id, name, pubkey)
+        }
+        UpdateFee { fee } => fees::execute::update_fee(deps, env, info, fee),
+        #[cfg(feature = "permits")]
+        SetOwnerPubkey { counter_id, pubkey } => {
+            permits::execute::set_owner_pubkey(deps, env, info, counter_id, pubkey)
+        }
+        #[cfg(feature = "permits")]
+        WithdrawWithPermit { permit, signature } => {
+            permits::execute::withdraw_with_permit(deps, env, info, permit, signature)
+        }
+        #[cfg(feature = "export")]
+        ImportState { snapshot } => contract::execute::import_state(deps, env, info, *snapshot),
     }
 }
 
@@ -51,6 +124,44 @@ pub fn execute(
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     use msg::QueryMsg::*;
     match msg {
-        Value {} => to_binary(&contract::query::value(deps)?),
+        Value { counter_id } => to_json_binary(&contract::query::value(deps, counter_id)?),
+        ValueAtHeight { counter_id, height } => {
+            to_json_binary(&contract::query::value_at_height(deps, counter_id, height)?)
+        }
+        Config { counter_id } => to_json_binary(&contract::query::config(deps, counter_id)?),
+        Counters { start_after, limit } => {
+            to_json_binary(&contract::query::counters(deps, start_after, limit)?)
+        }
+        #[cfg(feature = "matching")]
+        Matching { counter_id } => to_json_binary(&contract::query::matching(deps, counter_id)?),
+        #[cfg(feature = "rounds")]
+        Round { round_id } => to_json_binary(&rounds::query::round(deps, round_id)?),
+        TierThresholds { counter_id } => {
+            to_json_binary(&tiers::query::tier_thresholds(deps, counter_id)?)
+        }
+        DonorTier { counter_id, address } => {
+            to_json_binary(&tiers::query::donor_tier(deps, counter_id, address)?)
+        }
+        DonorsByTier { counter_id, tier, start_after, limit } => to_json_binary(
+            &tiers::query::donors_by_tier(deps, counter_id, tier, start_after, limit)?,
+        ),
+        Attestors { counter_id } => {
+            to_json_binary(&attestations::query::attestors(deps, counter_id)?)
+        }
+        VerifiedDonors { counter_id, start_after, limit } => to_json_binary(
+            &attestations::query::verified_donors(deps, counter_id, start_after, limit)?,
+        ),
+        FeeReport {} => to_json_binary(&fees::query::fee_report(deps)?),
+        #[cfg(feature = "permits")]
+        OwnerPubkey { counter_id } => {
+            to_json_binary(&permits::query::owner_pubkey(deps, counter_id)?)
+        }
+        AuditLog { start_after, limit } => {
+            to_json_binary(&audit::query::audit_log(deps, start_after, limit)?)
+        }
+        #[cfg(feature = "export")]
+        ExportState { start_after, limit } => {
+            to_json_binary(&contract::query::export_state(deps, start_after, limit)?)
+        }
     }
 }
@@ -1,15 +1,51 @@
-use cosmwasm_std::{Addr, Coin, StdResult, StdError, Empty};
-use crate::{InstantiateMsg, msg::{ValueResp, QueryMsg, ExecuteMsg}, error::ContractError};
+use cosmwasm_std::{Addr, Coin, StdResult, StdError};
+#[cfg(feature = "matching")]
+use cosmwasm_std::{Decimal, Uint128};
+use crate::{InstantiateMsg, msg::{AuditLogResp, VerifiedDonorsResp, ConfigResp, CountersResp, DonorTierResp, DonorsByTierResp, ExecuteMsg, FeeReportResp, MigrateMsg, QueryMsg, Tier, ValueResp}, error::ContractError};
+#[cfg(feature = "matching")]
+use crate::msg::MatchingResp;
+#[cfg(feature = "rounds")]
+use crate::msg::RoundResp;
+#[cfg(feature = "export")]
+use crate::msg::{SnapshotCursor, StateSnapshot};
+use counting_contract_interface::{CountingContractAddr, CountingQuerier};
 use crate::{execute, instantiate, query, migrate};
-use cw_multi_test::{App, Executor, ContractWrapper};
+use cw_multi_test::{App, AppResponse, Executor, ContractWrapper};
+mod api;
+#[cfg(any(test, feature = "scenario"))]
+pub mod scenario;
+pub mod state_machine;
+mod suite;
+pub mod transcript;
 #[cfg(test)]
 mod tests;
+#[cfg(all(test, feature = "vm"))]
+mod wasm;
+
+pub use api::CountingApi;
+pub use suite::{Suite, SuiteBuilder};
+
+//errors raised by multitest itself, like a failed admin check, are kept as generic errors
+fn into_std_error(err: anyhow::Error) -> StdError {
+    err.downcast()
+        .unwrap_or_else(|err| StdError::generic_err(format!("{err:#}")))
+}
+
+pub(crate) fn into_contract_error(err: anyhow::Error) -> ContractError {
+    err.downcast()
+        .unwrap_or_else(|err| ContractError::Std(into_std_error(err)))
+}
+
+#[derive(Debug)]
 pub struct CountingContract(Addr);
 
 impl CountingContract {
     pub fn addr(&self) -> &Addr {
         &self.0
     }
+    pub fn as_addr(&self) -> CountingContractAddr {
+        CountingContractAddr(self.0.clone())
+    }
     pub fn store_code(app: &mut App) -> u64 {
         let contract = ContractWrapper::new(execute, instantiate, query).with_migrate(migrate);
         app.store_code(Box::new(contract))
@@ -23,42 +59,226 @@ impl CountingContract {
         label: &str, 
         minimal_donation: Coin
     ) -> StdResult<CountingContract> {
-        app.instantiate_contract(
-            code_id, 
-            sender.clone(), 
-            &InstantiateMsg { minimal_donation, counter: 0 }, 
-            &[], 
+        let msg = InstantiateMsg::new(minimal_donation);
+        Self::instantiate_with_msg(app, code_id, sender, admin, label, &msg)
+            .map_err(|err| match err {
+                ContractError::Std(err) => err,
+                err => StdError::generic_err(err.to_string()),
+            })
+    }
+
+    #[track_caller]
+    pub fn instantiate_with_msg(
+        app: &mut App,
+        code_id: u64,
+        sender: &Addr,
+        admin: Option<&Addr>,
+        label: &str,
+        msg: &InstantiateMsg,
+    ) -> Result<CountingContract, ContractError> {
+        let msg = CountingContractAddr::instantiate_msg(
+            code_id,
+            msg,
             label,
             admin.map(Addr::to_string),
-        )
-        .map_err(|err| err.downcast().unwrap())
-        .map(CountingContract)
+        )?;
+        let resp = app
+            .execute(sender.clone(), msg)
+            .map_err(into_contract_error)?;
+
+        //multitest reports the new address in the instantiate event
+        let addr = resp
+            .events
+            .iter()
+            .filter(|ev| ev.ty == "instantiate")
+            .flat_map(|ev| &ev.attributes)
+            .find(|attr| attr.key == "_contract_addr")
+            .map(|attr| Addr::unchecked(&attr.value))
+            .ok_or_else(|| StdError::generic_err("instantiate event without contract address"))?;
+
+        Ok(CountingContract(addr))
     }
 
     #[track_caller]
     pub fn migrate(app: &mut App, sender: &Addr, contract: &Addr, code_id: u64) -> StdResult<Self> {
-        app.migrate_contract(sender.clone(), contract.clone(), &Empty {}, code_id)
-            .map_err(|err| err.downcast::<StdError>().unwrap())?;
-        Ok(CountingContract(contract.clone()))
-        }
+        Self::migrate_with_msg(app, sender, contract, code_id, &MigrateMsg::default())
+    }
+
+    #[track_caller]
+    pub fn migrate_with_msg(
+        app: &mut App,
+        sender: &Addr,
+        contract: &Addr,
+        code_id: u64,
+        msg: &MigrateMsg,
+    ) -> StdResult<Self> {
+        let contract = CountingContract(contract.clone());
+        app.execute(sender.clone(), contract.as_addr().migrate_msg(code_id, msg)?)
+            .map_err(into_std_error)?;
+        Ok(contract)
+    }
 
     #[track_caller]
     pub fn donate(&self, app: &mut App, sender: &Addr, funds: &[Coin]) -> Result<(), ContractError> {
-        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::Donate{}, funds)
-        .map_err(|err| err.downcast::<ContractError>().unwrap())?;
+        app.execute(sender.clone(), self.as_addr().donate_msg(funds.to_vec())?)
+        .map_err(into_contract_error)?;
 
         Ok(())
     }
 
     #[track_caller]
     pub fn withdraw(&self, app: &mut App, sender: &Addr) -> Result<(), ContractError> {
-        app.execute_contract(sender.clone(), self.0.clone(), &ExecuteMsg::Withdraw {  }, &[])
-        .map_err(|err| err.downcast::<ContractError>().unwrap())?;
+        app.execute(sender.clone(), self.as_addr().withdraw_msg()?)
+        .map_err(into_contract_error)?;
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn create_counter(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        id: &str,
+        minimal_donation: Coin,
+        owner: Option<&Addr>,
+    ) -> Result<(), ContractError> {
+        let msg = self.as_addr().create_counter_msg(id, minimal_donation, owner.map(Addr::to_string))?;
+        app.execute(sender.clone(), msg)
+        .map_err(into_contract_error)?;
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn donate_to(&self, app: &mut App, sender: &Addr, counter_id: &str, funds: &[Coin]) -> Result<(), ContractError> {
+        app.execute(sender.clone(), self.as_addr().donate_to_msg(counter_id, funds.to_vec())?)
+        .map_err(into_contract_error)?;
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn withdraw_from(&self, app: &mut App, sender: &Addr, counter_id: &str) -> Result<(), ContractError> {
+        app.execute(sender.clone(), self.as_addr().withdraw_from_msg(counter_id)?)
+        .map_err(into_contract_error)?;
+
+        Ok(())
+    }
+
+    //for messages without a dedicated helper
+    #[track_caller]
+    pub fn execute(&self, app: &mut App, sender: &Addr, msg: &ExecuteMsg, funds: &[Coin]) -> Result<AppResponse, ContractError> {
+        app.execute(sender.clone(), self.as_addr().execute_msg(msg, funds.to_vec())?)
+        .map_err(into_contract_error)
+    }
+
+    #[cfg(feature = "matching")]
+    #[track_caller]
+    pub fn fund_matching(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        ratio: Decimal,
+        cap: u128,
+        funds: &[Coin],
+    ) -> Result<(), ContractError> {
+        let msg = ExecuteMsg::FundMatching { counter_id: None, ratio, cap: Uint128::new(cap) };
+        app.execute(sender.clone(), self.as_addr().execute_msg(&msg, funds.to_vec())?)
+        .map_err(into_contract_error)?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "matching")]
+    #[track_caller]
+    pub fn close_matching(&self, app: &mut App, sender: &Addr) -> Result<(), ContractError> {
+        let msg = ExecuteMsg::CloseMatching { counter_id: None };
+        app.execute(sender.clone(), self.as_addr().execute_msg(&msg, vec![])?)
+        .map_err(into_contract_error)?;
 
         Ok(())
     }
 
     pub fn query_value(&self, app: &App) -> StdResult<ValueResp> {
-        app.wrap().query_wasm_smart(self.0.clone(), &QueryMsg::Value {  },)
+        app.wrap().value(self.0.clone())
+    }
+
+    pub fn query_value_at_height(&self, app: &App, height: u64) -> StdResult<ValueResp> {
+        let msg = QueryMsg::ValueAtHeight { counter_id: None, height };
+        app.wrap().query_wasm_smart(self.0.clone(), &msg)
+    }
+
+    pub fn query_config(&self, app: &App) -> StdResult<ConfigResp> {
+        app.wrap().config(self.0.clone())
+    }
+
+    #[cfg(feature = "matching")]
+    pub fn query_matching(&self, app: &App) -> StdResult<MatchingResp> {
+        app.wrap().matching(self.0.clone(), None)
+    }
+
+    #[cfg(feature = "rounds")]
+    pub fn query_round(&self, app: &App, round_id: &str) -> StdResult<RoundResp> {
+        let msg = QueryMsg::Round { round_id: round_id.to_owned() };
+        app.wrap().query_wasm_smart(self.0.clone(), &msg)
+    }
+
+    pub fn query_audit_log(&self, app: &App, start_after: Option<u64>, limit: Option<u32>) -> StdResult<AuditLogResp> {
+        app.wrap().query_wasm_smart(self.0.clone(), &QueryMsg::AuditLog { start_after, limit })
+    }
+
+    pub fn query_verified_donors(&self, app: &App) -> StdResult<VerifiedDonorsResp> {
+        let msg = QueryMsg::VerifiedDonors { counter_id: None, start_after: None, limit: None };
+        app.wrap().query_wasm_smart(self.0.clone(), &msg)
+    }
+
+    pub fn query_fee_report(&self, app: &App) -> StdResult<FeeReportResp> {
+        app.wrap().query_wasm_smart(self.0.clone(), &QueryMsg::FeeReport {})
+    }
+
+    pub fn query_donor_tier(&self, app: &App, address: &Addr) -> StdResult<DonorTierResp> {
+        let msg = QueryMsg::DonorTier { counter_id: None, address: address.to_string() };
+        app.wrap().query_wasm_smart(self.0.clone(), &msg)
+    }
+
+    pub fn query_donors_by_tier(&self, app: &App, tier: Tier) -> StdResult<Vec<Addr>> {
+        let msg = QueryMsg::DonorsByTier { counter_id: None, tier, start_after: None, limit: None };
+        app.wrap()
+            .query_wasm_smart::<DonorsByTierResp>(self.0.clone(), &msg)
+            .map(|resp| resp.donors)
+    }
+
+    pub fn query_counter_value(&self, app: &App, counter_id: &str) -> StdResult<ValueResp> {
+        app.wrap().counter_value(self.0.clone(), counter_id)
+    }
+
+    pub fn query_counters(&self, app: &App, start_after: Option<&str>, limit: Option<u32>) -> StdResult<CountersResp> {
+        app.wrap().counters(self.0.clone(), start_after.map(str::to_owned), limit)
+    }
+
+    #[cfg(feature = "export")]
+    pub fn query_export_state(
+        &self,
+        app: &App,
+        start_after: Option<SnapshotCursor>,
+        limit: Option<u32>,
+    ) -> StdResult<StateSnapshot> {
+        app.wrap().export_state(self.0.clone(), start_after, limit)
+    }
+
+    #[cfg(feature = "export")]
+    pub fn import_state(&self, app: &mut App, sender: &Addr, snapshot: StateSnapshot) -> Result<(), ContractError> {
+        let msg = ExecuteMsg::ImportState { snapshot: Box::new(snapshot) };
+        app.execute(sender.clone(), self.as_addr().execute_msg(&msg, vec![])?)
+        .map_err(into_contract_error)?;
+
+        Ok(())
+    }
+}
+//wraps counters instantiated by other contracts, like the factory
+impl From<Addr> for CountingContract {
+    fn from(addr: Addr) -> Self {
+        Self(addr)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,148 @@
+use cosmwasm_std::{Addr, Coin, StdError, StdResult};
+use counting_contract_0_1_0::error::ContractError as ContractError_0_1_0;
+use counting_contract_0_1_0::msg::ExecuteMsg as ExecuteMsg_0_1_0;
+use counting_contract_0_1_0::multitest::CountingContract as CountingContract_0_1_0;
+use cw_multi_test::{App, Executor};
+
+use super::CountingContract;
+use crate::error::ContractError;
+use crate::msg::ValueResp;
+
+//common interface over every released version of the multitest wrapper
+//so a single test body can be run against all of them
+pub trait CountingApi: Sized {
+    const VERSION: &'static str;
+
+    fn store_code(app: &mut App) -> u64;
+
+    fn instantiate(
+        app: &mut App,
+        code_id: u64,
+        sender: &Addr,
+        admin: Option<&Addr>,
+        label: &str,
+        minimal_donation: Coin,
+    ) -> StdResult<Self>;
+
+    fn addr(&self) -> &Addr;
+
+    fn donate(&self, app: &mut App, sender: &Addr, funds: &[Coin]) -> Result<(), ContractError>;
+
+    fn withdraw(&self, app: &mut App, sender: &Addr) -> Result<(), ContractError>;
+
+    fn query_value(&self, app: &App) -> StdResult<ValueResp>;
+
+    fn supports_reset(&self) -> bool {
+        false
+    }
+
+    fn reset(&self, _app: &mut App, _sender: &Addr) -> Result<(), ContractError> {
+        Err(StdError::generic_err(format!("reset is not supported by {}", Self::VERSION)).into())
+    }
+
+    fn supports_migrate(&self) -> bool {
+        false
+    }
+
+    fn migrate(&self, _app: &mut App, _sender: &Addr, _code_id: u64) -> StdResult<()> {
+        Err(StdError::generic_err(format!("migrate is not supported by {}", Self::VERSION)))
+    }
+}
+
+fn from_legacy_error(err: ContractError_0_1_0) -> ContractError {
+    match err {
+        ContractError_0_1_0::Std(err) => ContractError::Std(err),
+        ContractError_0_1_0::Unauthorized { owner } => ContractError::Unauthorized { owner },
+    }
+}
+
+impl CountingApi for CountingContract_0_1_0 {
+    const VERSION: &'static str = "0.1.0";
+
+    fn store_code(app: &mut App) -> u64 {
+        CountingContract_0_1_0::store_code(app)
+    }
+
+    fn instantiate(
+        app: &mut App,
+        code_id: u64,
+        sender: &Addr,
+        admin: Option<&Addr>,
+        label: &str,
+        minimal_donation: Coin,
+    ) -> StdResult<Self> {
+        CountingContract_0_1_0::instantiate(app, code_id, sender, admin, label, minimal_donation)
+    }
+
+    fn addr(&self) -> &Addr {
+        CountingContract_0_1_0::addr(self)
+    }
+
+    fn donate(&self, app: &mut App, sender: &Addr, funds: &[Coin]) -> Result<(), ContractError> {
+        CountingContract_0_1_0::donate(self, app, sender, funds).map_err(from_legacy_error)
+    }
+
+    fn withdraw(&self, app: &mut App, sender: &Addr) -> Result<(), ContractError> {
+        CountingContract_0_1_0::withdraw(self, app, sender).map_err(from_legacy_error)
+    }
+
+    fn query_value(&self, app: &App) -> StdResult<ValueResp> {
+        let resp = CountingContract_0_1_0::query_value(self, app)?;
+        Ok(ValueResp { value: resp.value })
+    }
+
+    fn supports_reset(&self) -> bool {
+        true
+    }
+
+    fn reset(&self, app: &mut App, sender: &Addr) -> Result<(), ContractError> {
+        app.execute_contract(sender.clone(), self.addr().clone(), &ExecuteMsg_0_1_0::Reset {}, &[])
+            .map_err(|err| from_legacy_error(err.downcast().unwrap()))?;
+
+        Ok(())
+    }
+}
+
+impl CountingApi for CountingContract {
+    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+
+    fn store_code(app: &mut App) -> u64 {
+        CountingContract::store_code(app)
+    }
+
+    fn instantiate(
+        app: &mut App,
+        code_id: u64,
+        sender: &Addr,
+        admin: Option<&Addr>,
+        label: &str,
+        minimal_donation: Coin,
+    ) -> StdResult<Self> {
+        CountingContract::instantiate(app, code_id, sender, admin, label, minimal_donation)
+    }
+
+    fn addr(&self) -> &Addr {
+        CountingContract::addr(self)
+    }
+
+    fn donate(&self, app: &mut App, sender: &Addr, funds: &[Coin]) -> Result<(), ContractError> {
+        CountingContract::donate(self, app, sender, funds)
+    }
+
+    fn withdraw(&self, app: &mut App, sender: &Addr) -> Result<(), ContractError> {
+        CountingContract::withdraw(self, app, sender)
+    }
+
+    fn query_value(&self, app: &App) -> StdResult<ValueResp> {
+        CountingContract::query_value(self, app)
+    }
+
+    fn supports_migrate(&self) -> bool {
+        true
+    }
+
+    fn migrate(&self, app: &mut App, sender: &Addr, code_id: u64) -> StdResult<()> {
+        CountingContract::migrate(app, sender, self.addr(), code_id)?;
+        Ok(())
+    }
+}
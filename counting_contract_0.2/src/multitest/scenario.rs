@@ -0,0 +1,538 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use cosmwasm_std::{Addr, Coin};
+use counting_contract_0_1_0::multitest::CountingContract as CountingContract_0_1_0;
+use cw_multi_test::App;
+use serde::Deserialize;
+
+use super::{CountingApi, CountingContract};
+use crate::error::ContractError;
+
+//donation scenario described in json or toml, run against cw-multi-test
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub balances: BTreeMap<String, Vec<Coin>>,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum Version {
+    #[serde(rename = "0.1")]
+    V0_1,
+    #[serde(rename = "0.2")]
+    V0_2,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    Instantiate {
+        version: Version,
+        sender: String,
+        admin: Option<String>,
+        #[serde(default = "default_label")]
+        label: String,
+        minimal_donation: Coin,
+        expect_error: Option<String>,
+    },
+    Donate {
+        sender: String,
+        #[serde(default)]
+        funds: Vec<Coin>,
+        expect_error: Option<String>,
+    },
+    Withdraw {
+        sender: String,
+        expect_error: Option<String>,
+    },
+    Migrate {
+        sender: String,
+        version: Version,
+        expect_error: Option<String>,
+    },
+    AdvanceBlock {
+        #[serde(default = "default_blocks")]
+        blocks: u64,
+        #[serde(default)]
+        seconds: u64,
+    },
+    Query {
+        value: Option<u64>,
+        //balances by address, "contract" stands for the instantiated contract
+        #[serde(default)]
+        balances: BTreeMap<String, Vec<Coin>>,
+    },
+}
+
+fn default_label() -> String {
+    "Counting Contract".to_owned()
+}
+
+fn default_blocks() -> u64 {
+    1
+}
+
+impl Step {
+    fn action(&self) -> &'static str {
+        match self {
+            Step::Instantiate { .. } => "instantiate",
+            Step::Donate { .. } => "donate",
+            Step::Withdraw { .. } => "withdraw",
+            Step::Migrate { .. } => "migrate",
+            Step::AdvanceBlock { .. } => "advance_block",
+            Step::Query { .. } => "query",
+        }
+    }
+}
+
+impl Scenario {
+    pub fn from_json(data: &str) -> Result<Self, String> {
+        serde_json::from_str(data).map_err(|err| err.to_string())
+    }
+
+    pub fn from_toml(data: &str) -> Result<Self, String> {
+        toml::from_str(data).map_err(|err| err.to_string())
+    }
+
+    //picks the format by extension, anything but .toml is read as json
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&data),
+            _ => Self::from_json(&data),
+        }
+        .map_err(|err| format!("{}: {err}", path.display()))
+    }
+
+    pub fn run(&self) -> Report {
+        self.run_on(&mut MultiTest::new())
+    }
+
+    pub fn run_on(&self, backend: &mut impl Backend) -> Report {
+        for (addr, coins) in &self.balances {
+            backend.init_balance(&Addr::unchecked(addr), coins.clone());
+        }
+
+        let steps = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(idx, step)| {
+                let (failures, failed) = run_step(backend, step);
+                StepReport {
+                    index: idx + 1,
+                    action: step.action(),
+                    failures,
+                    observed: failed.map(|failed| observe(backend, failed)),
+                }
+            })
+            .collect();
+
+        Report {
+            name: self.name.clone(),
+            steps,
+        }
+    }
+}
+
+//chain a scenario runs on, contract calls go to the last instantiated contract
+pub trait Backend {
+    fn init_balance(&mut self, addr: &Addr, coins: Vec<Coin>);
+
+    fn instantiate(
+        &mut self,
+        version: Version,
+        sender: &Addr,
+        admin: Option<&Addr>,
+        label: &str,
+        minimal_donation: Coin,
+    ) -> Result<(), String>;
+
+    fn contract(&self) -> Option<Addr>;
+
+    fn donate(&mut self, sender: &Addr, funds: &[Coin]) -> Result<(), String>;
+
+    fn withdraw(&mut self, sender: &Addr) -> Result<(), String>;
+
+    fn migrate(&mut self, sender: &Addr, version: Version) -> Result<(), String>;
+
+    fn advance_block(&mut self, blocks: u64, seconds: u64);
+
+    fn query_value(&mut self) -> Result<u64, String>;
+
+    fn query_balance(&mut self, addr: &Addr) -> Result<Vec<Coin>, String>;
+}
+
+enum Deployed {
+    V0_1(CountingContract_0_1_0),
+    V0_2(CountingContract),
+}
+
+impl Deployed {
+    fn addr(&self) -> &Addr {
+        match self {
+            Deployed::V0_1(contract) => CountingApi::addr(contract),
+            Deployed::V0_2(contract) => CountingApi::addr(contract),
+        }
+    }
+}
+
+pub struct MultiTest {
+    app: App,
+    code_ids: BTreeMap<&'static str, u64>,
+    contract: Option<Deployed>,
+}
+
+impl Default for MultiTest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiTest {
+    pub fn new() -> Self {
+        Self {
+            app: App::default(),
+            code_ids: BTreeMap::new(),
+            contract: None,
+        }
+    }
+
+    fn code_id(&mut self, version: Version) -> u64 {
+        let app = &mut self.app;
+        match version {
+            Version::V0_1 => *self
+                .code_ids
+                .entry("0.1")
+                .or_insert_with(|| <CountingContract_0_1_0 as CountingApi>::store_code(app)),
+            Version::V0_2 => *self
+                .code_ids
+                .entry("0.2")
+                .or_insert_with(|| <CountingContract as CountingApi>::store_code(app)),
+        }
+    }
+
+    fn deployed(&self) -> Result<&Deployed, String> {
+        self.contract
+            .as_ref()
+            .ok_or_else(|| "no contract instantiated yet".to_owned())
+    }
+}
+
+impl Backend for MultiTest {
+    fn init_balance(&mut self, addr: &Addr, coins: Vec<Coin>) {
+        self.app.init_modules(|router, _api, storage| {
+            router.bank.init_balance(storage, addr, coins).unwrap()
+        });
+    }
+
+    fn instantiate(
+        &mut self,
+        version: Version,
+        sender: &Addr,
+        admin: Option<&Addr>,
+        label: &str,
+        minimal_donation: Coin,
+    ) -> Result<(), String> {
+        let code_id = self.code_id(version);
+        let contract = match version {
+            Version::V0_1 => <CountingContract_0_1_0 as CountingApi>::instantiate(
+                &mut self.app,
+                code_id,
+                sender,
+                admin,
+                label,
+                minimal_donation,
+            )
+            .map(Deployed::V0_1),
+            Version::V0_2 => <CountingContract as CountingApi>::instantiate(
+                &mut self.app,
+                code_id,
+                sender,
+                admin,
+                label,
+                minimal_donation,
+            )
+            .map(Deployed::V0_2),
+        }
+        .map_err(|err| err.to_string())?;
+        self.contract = Some(contract);
+        Ok(())
+    }
+
+    fn contract(&self) -> Option<Addr> {
+        self.contract.as_ref().map(|contract| contract.addr().clone())
+    }
+
+    fn donate(&mut self, sender: &Addr, funds: &[Coin]) -> Result<(), String> {
+        let app = &mut self.app;
+        match self.contract.as_ref().ok_or("no contract instantiated yet")? {
+            Deployed::V0_1(contract) => CountingApi::donate(contract, app, sender, funds),
+            Deployed::V0_2(contract) => CountingApi::donate(contract, app, sender, funds),
+        }
+        .map_err(|err: ContractError| err.to_string())
+    }
+
+    fn withdraw(&mut self, sender: &Addr) -> Result<(), String> {
+        let app = &mut self.app;
+        match self.contract.as_ref().ok_or("no contract instantiated yet")? {
+            Deployed::V0_1(contract) => CountingApi::withdraw(contract, app, sender),
+            Deployed::V0_2(contract) => CountingApi::withdraw(contract, app, sender),
+        }
+        .map_err(|err: ContractError| err.to_string())
+    }
+
+    fn migrate(&mut self, sender: &Addr, version: Version) -> Result<(), String> {
+        let addr = self.deployed()?.addr().clone();
+        let code_id = self.code_id(version);
+        let contract = CountingContract::migrate(&mut self.app, sender, &addr, code_id)
+            .map_err(|err| err.to_string())?;
+        self.contract = Some(Deployed::V0_2(contract));
+        Ok(())
+    }
+
+    fn advance_block(&mut self, blocks: u64, seconds: u64) {
+        self.app.update_block(|block| {
+            block.height += blocks;
+            block.time = block.time.plus_seconds(seconds);
+        });
+    }
+
+    fn query_value(&mut self) -> Result<u64, String> {
+        match self.deployed()? {
+            Deployed::V0_1(contract) => CountingApi::query_value(contract, &self.app),
+            Deployed::V0_2(contract) => CountingApi::query_value(contract, &self.app),
+        }
+        .map(|resp| resp.value)
+        .map_err(|err| err.to_string())
+    }
+
+    fn query_balance(&mut self, addr: &Addr) -> Result<Vec<Coin>, String> {
+        self.app
+            .wrap()
+            .query_all_balances(addr)
+            .map_err(|err| err.to_string())
+    }
+}
+
+//state of the contract after a step, compared between backends
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Observed {
+    pub failed: bool,
+    pub value: Option<u64>,
+    pub balance: Vec<Coin>,
+}
+
+fn observe(backend: &mut impl Backend, failed: bool) -> Observed {
+    let Some(contract) = backend.contract() else {
+        return Observed {
+            failed,
+            value: None,
+            balance: vec![],
+        };
+    };
+    Observed {
+        failed,
+        value: backend.query_value().ok(),
+        balance: backend.query_balance(&contract).unwrap_or_default(),
+    }
+}
+
+//wrappers panic on errors they cannot downcast, those are reported as step failures;
+//returns the failures and whether the call failed, unless the step panicked
+fn run_step(backend: &mut impl Backend, step: &Step) -> (Vec<String>, Option<bool>) {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| execute(backend, step)));
+    panic::set_hook(hook);
+
+    match result {
+        Ok((failures, failed)) => (failures, Some(failed)),
+        Err(payload) => {
+            let msg = payload
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| payload.downcast_ref::<&str>().map(|msg| msg.to_string()))
+                .unwrap_or_default();
+            let msg = msg.split("\n\nStack backtrace:").next().unwrap_or_default();
+            (vec![format!("step panicked: {msg}")], None)
+        }
+    }
+}
+
+fn execute(backend: &mut impl Backend, step: &Step) -> (Vec<String>, bool) {
+    let needs_contract = !matches!(
+        step,
+        Step::Instantiate { .. } | Step::AdvanceBlock { .. } | Step::Query { value: None, .. }
+    );
+    if needs_contract && backend.contract().is_none() {
+        return (vec!["no contract instantiated yet".to_owned()], true);
+    }
+
+    let (result, expect_error) = match step {
+        Step::Instantiate {
+            version,
+            sender,
+            admin,
+            label,
+            minimal_donation,
+            expect_error,
+        } => {
+            let admin = admin.as_ref().map(Addr::unchecked);
+            let result = backend.instantiate(
+                *version,
+                &Addr::unchecked(sender),
+                admin.as_ref(),
+                label,
+                minimal_donation.clone(),
+            );
+            (result, expect_error)
+        }
+        Step::Donate {
+            sender,
+            funds,
+            expect_error,
+        } => (backend.donate(&Addr::unchecked(sender), funds), expect_error),
+        Step::Withdraw {
+            sender,
+            expect_error,
+        } => (backend.withdraw(&Addr::unchecked(sender)), expect_error),
+        Step::Migrate {
+            sender,
+            version,
+            expect_error,
+        } => {
+            let result = match version {
+                Version::V0_1 => Err("0.1 has no migrate entry point".to_owned()),
+                Version::V0_2 => backend.migrate(&Addr::unchecked(sender), *version),
+            };
+            (result, expect_error)
+        }
+        Step::AdvanceBlock { blocks, seconds } => {
+            backend.advance_block(*blocks, *seconds);
+            return (vec![], false);
+        }
+        Step::Query { value, balances } => return (query(backend, value, balances), false),
+    };
+
+    let failed = result.is_err();
+    (check_error(result.err(), expect_error), failed)
+}
+
+fn query(
+    backend: &mut impl Backend,
+    value: &Option<u64>,
+    balances: &BTreeMap<String, Vec<Coin>>,
+) -> Vec<String> {
+    let mut failures = vec![];
+    if let Some(expected) = value {
+        match backend.query_value() {
+            Ok(actual) if actual == *expected => (),
+            Ok(actual) => failures.push(diff("value", expected, &actual)),
+            Err(err) => failures.push(format!("value query failed: {err}")),
+        }
+    }
+    for (addr, expected) in balances {
+        let addr = match (addr.as_str(), backend.contract()) {
+            ("contract", Some(contract)) => contract,
+            _ => Addr::unchecked(addr),
+        };
+        let mut expected = expected.clone();
+        expected.sort_by(|a, b| a.denom.cmp(&b.denom));
+        match backend.query_balance(&addr) {
+            Ok(actual) if actual == expected => (),
+            Ok(actual) => failures.push(diff(
+                &format!("balance of {addr}"),
+                &Coins(&expected),
+                &Coins(&actual),
+            )),
+            Err(err) => failures.push(format!("balance query failed: {err}")),
+        }
+    }
+    failures
+}
+
+//an expected error matches when the actual one contains it
+fn check_error(actual: Option<String>, expected: &Option<String>) -> Vec<String> {
+    match (actual, expected) {
+        (None, None) => vec![],
+        (Some(actual), Some(expected)) if actual.contains(expected.as_str()) => vec![],
+        (actual, expected) => vec![diff(
+            "error",
+            &expected.as_deref().unwrap_or("<none>"),
+            &actual.as_deref().unwrap_or("<none>"),
+        )],
+    }
+}
+
+fn diff(what: &str, expected: &dyn fmt::Display, actual: &dyn fmt::Display) -> String {
+    format!("{what} differs\n  - expected: {expected}\n  + actual:   {actual}")
+}
+
+struct Coins<'a>(&'a [Coin]);
+
+impl fmt::Display for Coins<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "[]");
+        }
+        let coins: Vec<_> = self.0.iter().map(Coin::to_string).collect();
+        write!(f, "{}", coins.join(","))
+    }
+}
+
+pub struct StepReport {
+    pub index: usize,
+    pub action: &'static str,
+    pub failures: Vec<String>,
+    //missing when the step panicked
+    pub observed: Option<Observed>,
+}
+
+impl StepReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+pub struct Report {
+    pub name: String,
+    pub steps: Vec<StepReport>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(StepReport::passed)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "scenario {}", self.name)?;
+        for step in &self.steps {
+            let status = if step.passed() { "PASS" } else { "FAIL" };
+            writeln!(f, "  {status} #{} {}", step.index, step.action)?;
+            for failure in &step.failures {
+                for line in failure.lines() {
+                    writeln!(f, "       {line}")?;
+                }
+            }
+        }
+        let failed = self.steps.iter().filter(|step| !step.passed()).count();
+        write!(
+            f,
+            "{}: {} steps, {} failed",
+            if failed == 0 { "PASSED" } else { "FAILED" },
+            self.steps.len(),
+            failed
+        )
+    }
+}
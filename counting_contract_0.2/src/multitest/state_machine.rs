@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use cosmwasm_std::{Addr, Coin};
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+use super::{CountingApi, Suite, SuiteBuilder};
+
+pub const SENDERS: [&str; 3] = ["owner", "alice", "bob"];
+pub const DENOMS: [&str; 2] = ["atom", "btc"];
+const INITIAL_BALANCE: u128 = 1_000_000;
+const MINIMAL_DONATION: u128 = 10;
+
+//single call made against the contract, senders are indexes into SENDERS
+#[derive(Clone, Debug)]
+pub enum Op {
+    Donate { sender: usize, funds: Vec<Coin> },
+    Withdraw { sender: usize },
+    Migrate,
+    Query,
+}
+
+pub fn funds_strategy() -> impl Strategy<Value = Vec<Coin>> {
+    (
+        proptest::option::of(1..30u128),
+        proptest::option::of(1..30u128),
+    )
+        .prop_map(|(atom, btc)| {
+            let mut funds = vec![];
+            if let Some(amount) = atom {
+                funds.push(Coin::new(amount, DENOMS[0]));
+            }
+            if let Some(amount) = btc {
+                funds.push(Coin::new(amount, DENOMS[1]));
+            }
+            funds
+        })
+}
+
+pub fn op_strategy() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (0..SENDERS.len(), funds_strategy())
+            .prop_map(|(sender, funds)| Op::Donate { sender, funds }),
+        2 => (0..SENDERS.len()).prop_map(|sender| Op::Withdraw { sender }),
+        1 => Just(Op::Migrate),
+        1 => Just(Op::Query),
+    ]
+}
+
+pub fn ops_strategy(max_len: usize) -> impl Strategy<Value = Vec<Op>> {
+    proptest::collection::vec(op_strategy(), 0..max_len)
+}
+
+//what the contract is expected to hold after the ops applied so far
+#[derive(Default)]
+struct Model {
+    counter: u64,
+    donated: BTreeMap<String, u128>,
+    withdrawn: BTreeMap<String, u128>,
+}
+
+struct Harness {
+    suite: Suite,
+    model: Model,
+}
+
+impl Harness {
+    fn new(legacy: bool) -> Self {
+        let mut builder = SuiteBuilder::new()
+            .with_admin("admin")
+            .with_min_donation(Coin::new(MINIMAL_DONATION, DENOMS[0]));
+        for sender in SENDERS {
+            let funds: Vec<_> = DENOMS
+                .iter()
+                .map(|denom| Coin::new(INITIAL_BALANCE, *denom))
+                .collect();
+            builder = builder.with_funds(sender, &funds);
+        }
+        if legacy {
+            builder = builder.with_legacy_v0_1();
+        }
+
+        Self {
+            suite: builder.build(),
+            model: Model::default(),
+        }
+    }
+
+    fn donate(&mut self, sender: &Addr, funds: &[Coin]) -> Result<(), TestCaseError> {
+        let result = match &self.suite.legacy_contract {
+            Some(legacy) => CountingApi::donate(legacy, &mut self.suite.app, sender, funds),
+            None => CountingApi::donate(&self.suite.contract, &mut self.suite.app, sender, funds),
+        };
+        prop_assert!(result.is_ok(), "donate failed: {:?}", result);
+
+        if funds
+            .iter()
+            .any(|coin| coin.denom == DENOMS[0] && coin.amount.u128() >= MINIMAL_DONATION)
+        {
+            self.model.counter += 1;
+        }
+        for coin in funds {
+            *self.model.donated.entry(coin.denom.clone()).or_default() += coin.amount.u128();
+        }
+        Ok(())
+    }
+
+    fn withdraw(&mut self, sender: &Addr) -> Result<(), TestCaseError> {
+        let before = self.contract_balance();
+        if before.is_empty() && self.suite.legacy_contract.is_some() {
+            //0.1 sends an empty BankMsg here, which the bank module rejects
+            return Ok(());
+        }
+
+        let result = match &self.suite.legacy_contract {
+            Some(legacy) => CountingApi::withdraw(legacy, &mut self.suite.app, sender),
+            None => CountingApi::withdraw(&self.suite.contract, &mut self.suite.app, sender),
+        };
+
+        if *sender == self.suite.owner {
+            prop_assert!(result.is_ok(), "owner withdraw failed: {:?}", result);
+            for (denom, amount) in before {
+                *self.model.withdrawn.entry(denom).or_default() += amount;
+            }
+        } else {
+            prop_assert!(result.is_err(), "{} drained the contract", sender);
+            prop_assert_eq!(self.contract_balance(), before);
+        }
+        Ok(())
+    }
+
+    fn migrate(&mut self) -> Result<(), TestCaseError> {
+        //migrating again onto the same version must leave the state untouched
+        let result = self.suite.migrate();
+        prop_assert!(result.is_ok(), "migration failed: {:?}", result);
+        Ok(())
+    }
+
+    fn contract_balance(&self) -> BTreeMap<String, u128> {
+        self.suite
+            .app
+            .wrap()
+            .query_all_balances(self.suite.contract.addr())
+            .unwrap()
+            .into_iter()
+            .map(|coin| (coin.denom, coin.amount.u128()))
+            .collect()
+    }
+
+    fn check_invariants(&self) -> Result<(), TestCaseError> {
+        let value = CountingApi::query_value(&self.suite.contract, &self.suite.app)
+            .map_err(|err| TestCaseError::fail(err.to_string()))?
+            .value;
+        prop_assert_eq!(value, self.model.counter, "counter differs from qualifying donations");
+
+        let balance = self.contract_balance();
+        for denom in DENOMS {
+            let donated = self.model.donated.get(denom).copied().unwrap_or_default();
+            let withdrawn = self.model.withdrawn.get(denom).copied().unwrap_or_default();
+            let held = balance.get(denom).copied().unwrap_or_default();
+            prop_assert_eq!(held + withdrawn, donated, "balance mismatch for {}", denom);
+        }
+        Ok(())
+    }
+}
+
+//applies ops one by one and checks the invariants after every step
+pub fn run(legacy: bool, ops: &[Op]) -> Result<(), TestCaseError> {
+    let mut harness = Harness::new(legacy);
+
+    for op in ops {
+        match op {
+            Op::Donate { sender, funds } => {
+                harness.donate(&Addr::unchecked(SENDERS[*sender]), funds)?
+            }
+            Op::Withdraw { sender } => harness.withdraw(&Addr::unchecked(SENDERS[*sender]))?,
+            Op::Migrate => harness.migrate()?,
+            Op::Query => (),
+        }
+        harness.check_invariants()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use cosmwasm_std::{Addr, Coin, StdResult};
+use counting_contract_0_1_0::multitest::CountingContract as CountingContract_0_1_0;
+use cw_multi_test::App;
+
+use super::CountingContract;
+use crate::msg::{FeeConfig, InstantiateMsg};
+
+//collects the setup shared by most tests and builds a ready to use Suite
+pub struct SuiteBuilder {
+    funds: Vec<(Addr, Vec<Coin>)>,
+    owner: Addr,
+    admin: Option<Addr>,
+    minimal_donation: Coin,
+    grace_period: Option<u64>,
+    fee: Option<FeeConfig>,
+    legacy_v0_1: bool,
+}
+
+impl SuiteBuilder {
+    pub fn new() -> Self {
+        Self {
+            funds: vec![],
+            owner: Addr::unchecked("owner"),
+            admin: None,
+            minimal_donation: Coin::new(10, "atom"),
+            grace_period: None,
+            fee: None,
+            legacy_v0_1: false,
+        }
+    }
+
+    pub fn with_funds(mut self, addr: &str, funds: &[Coin]) -> Self {
+        self.funds.push((Addr::unchecked(addr), funds.to_vec()));
+        self
+    }
+
+    pub fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = Addr::unchecked(owner);
+        self
+    }
+
+    pub fn with_min_donation(mut self, minimal_donation: Coin) -> Self {
+        self.minimal_donation = minimal_donation;
+        self
+    }
+
+    pub fn with_admin(mut self, admin: &str) -> Self {
+        self.admin = Some(Addr::unchecked(admin));
+        self
+    }
+
+    pub fn with_grace_period(mut self, seconds: u64) -> Self {
+        self.grace_period = Some(seconds);
+        self
+    }
+
+    pub fn with_fee(mut self, fee: FeeConfig) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    //instantiates the contract from the 0.1 code so it can be migrated later
+    pub fn with_legacy_v0_1(mut self) -> Self {
+        self.legacy_v0_1 = true;
+        self
+    }
+
+    #[track_caller]
+    pub fn build(self) -> Suite {
+        let funds = self.funds;
+        let mut app = App::new(|router, _api, storage| {
+            for (addr, coins) in funds {
+                router.bank.init_balance(storage, &addr, coins).unwrap();
+            }
+        });
+
+        let code_id = CountingContract::store_code(&mut app);
+        let legacy_code_id = CountingContract_0_1_0::store_code(&mut app);
+
+        let (contract, legacy_contract) = if self.legacy_v0_1 {
+            let legacy = CountingContract_0_1_0::instantiate(
+                &mut app,
+                legacy_code_id,
+                &self.owner,
+                self.admin.as_ref(),
+                "Counting Contract",
+                self.minimal_donation,
+            )
+            .unwrap();
+            (CountingContract(legacy.addr().clone()), Some(legacy))
+        } else {
+            let msg = InstantiateMsg {
+                grace_period: self.grace_period,
+                fee: self.fee,
+                ..InstantiateMsg::new(self.minimal_donation)
+            };
+            let contract = CountingContract::instantiate_with_msg(
+                &mut app,
+                code_id,
+                &self.owner,
+                self.admin.as_ref(),
+                "Counting Contract",
+                &msg,
+            )
+            .unwrap();
+            (contract, None)
+        };
+
+        Suite {
+            app,
+            owner: self.owner,
+            admin: self.admin,
+            code_id,
+            legacy_code_id,
+            contract,
+            legacy_contract,
+        }
+    }
+}
+
+impl Default for SuiteBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Suite {
+    pub app: App,
+    pub owner: Addr,
+    pub admin: Option<Addr>,
+    pub code_id: u64,
+    pub legacy_code_id: u64,
+    //handle for the current code, points at the same address as legacy_contract before migration
+    pub contract: CountingContract,
+    pub legacy_contract: Option<CountingContract_0_1_0>,
+}
+
+impl Suite {
+    //migrates the contract to the current code, admin has to be set
+    #[track_caller]
+    pub fn migrate(&mut self) -> StdResult<()> {
+        let admin = self.admin.clone().expect("suite built without admin");
+        self.contract =
+            CountingContract::migrate(&mut self.app, &admin, self.contract.addr(), self.code_id)?;
+        self.legacy_contract = None;
+        Ok(())
+    }
+}
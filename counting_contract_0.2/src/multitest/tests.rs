@@ -1,159 +1,1247 @@
-use cosmwasm_std::{Empty, Addr, Coin, coins};
-use cw_multi_test::{App, ContractWrapper, Contract};
-use crate::{execute, instantiate, query, multitest::CountingContract};
-use crate::state::{State, STATE};
-use counting_contract_0_1_0::multitest::CountingContract as Counting_Contract_0_1_0;
-
-fn counting_contract() -> Box<dyn Contract<Empty>> {
-    let contract = ContractWrapper::new(execute, instantiate, query);
-    Box::new(contract)
-}
+use cosmwasm_std::{from_json, to_json_string, Addr, Binary, Coin, Uint128, coins};
+#[cfg(feature = "matching")]
+use cosmwasm_std::Decimal;
+use counting_contract_0_1_0::multitest::CountingContract as CountingContract_0_1_0;
+use cw_multi_test::{next_block, App};
+use cw_utils::PaymentError;
+use k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+#[cfg(feature = "permits")]
+use ed25519_zebra::{SigningKey as Ed25519SigningKey, VerificationKey as Ed25519VerificationKey};
+use proptest::prelude::*;
+use crate::error::ContractError;
+use crate::msg::{
+    Attestation, AuditKind, ConfigResp, ExecuteMsg, FeeConfig, FeeMode, InstantiateMsg, MigrateMsg, QueryMsg, Tier, TierThresholds,
+    DEFAULT_COUNTER,
+};
+#[cfg(feature = "permits")]
+use crate::msg::{OwnerPubkey, WithdrawPermit};
+#[cfg(feature = "export")]
+use crate::contract::MAX_SNAPSHOT_ENTRIES;
+#[cfg(feature = "matching")]
+use crate::contract::{MAX_ACTIVE_SPONSORS, MAX_MATCHING_RATIO};
+#[cfg(feature = "export")]
+use crate::msg::{
+    CounterSnapshot, DonorSnapshot, SnapshotCursor, SnapshotList, StateSnapshot, TierThresholdsSnapshot, SNAPSHOT_VERSION,
+};
+use counting_contract_interface::{attestation, audit, CountingQuerier};
+#[cfg(feature = "permits")]
+use counting_contract_interface::permit;
+use crate::multitest::scenario::Scenario;
+use crate::multitest::transcript::RecordingContract;
+use crate::multitest::{state_machine, CountingApi, CountingContract, SuiteBuilder};
+use crate::decoder::{self, KeyStatus, KnownValue, Layout};
+use crate::state::{CounterState, COUNTERS};
 
 #[test]
 fn query_value() {
-    let mut app = App::default();
-    let contract_id = app.store_code(counting_contract());
-    let sender = Addr::unchecked("sender");
-    let contract = CountingContract::instantiate(
-        &mut app, 
-        contract_id, 
-        &sender, 
-        None,
-        "Counting Contract",
-        Coin::new(10, "atom")
-        )
-    .unwrap();
-    
-    let resp = contract.query_value(&app).unwrap();
+    let suite = SuiteBuilder::new().build();
+
+    let resp = suite.contract.query_value(&suite.app).unwrap();
     assert_eq!(resp.value, 0);
 }
 
 #[test]
 fn donate() {
-    let mut app = App::default();
-    let contract_id = app.store_code(counting_contract());
+    let mut suite = SuiteBuilder::new().build();
     let sender = Addr::unchecked("sender");
-    let contract = CountingContract::instantiate(
-        &mut app, 
-        contract_id, 
-        &sender, 
-        None,
-        "Counting Contract",
-        Coin::new(10, "atom")
-        )
-    .unwrap();
 
-    contract.donate(&mut app, &sender, &[]).unwrap();
-    let resp = contract.query_value(&app).unwrap();
+    suite.contract.donate(&mut suite.app, &sender, &[]).unwrap();
+    let resp = suite.contract.query_value(&suite.app).unwrap();
     assert_eq!(resp.value, 0);
 }
 
 #[test]
 fn donate_with_funds() {
     let sender = Addr::unchecked("sender");
-    let mut app = App::new( |router, _api, storage| {
-        router
-            .bank
-            .init_balance(storage, &sender, coins(10, "atom"))
-            .unwrap()
-    });
-    let contract_id = app.store_code(counting_contract());
-    let contract = CountingContract::instantiate(
-        &mut app, 
-        contract_id, 
-        &sender, 
-        None,
-        "Counting Contract",
-        Coin::new(10, "atom")
-        )
-    .unwrap();
+    let mut suite = SuiteBuilder::new()
+        .with_funds("sender", &coins(10, "atom"))
+        .build();
 
-    contract.donate(&mut app, &sender, &coins(10, "atom")).unwrap();
-    let resp = contract.query_value(&app).unwrap();
+    suite.contract.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+    let resp = suite.contract.query_value(&suite.app).unwrap();
     assert_eq!(resp.value, 1);
 
     assert_eq!(
-        app.wrap().query_all_balances(contract.addr()).unwrap(),
+        suite.app.wrap().query_all_balances(suite.contract.addr()).unwrap(),
         coins(10, "atom")
     )
 }
 
+#[test]
+fn value_at_height() {
+    let sender = Addr::unchecked("sender");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("sender", &coins(30, "atom"))
+        .build();
+    let start = suite.app.block_info().height;
+
+    suite.contract.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+    suite.app.update_block(next_block);
+    suite.contract.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+    suite.contract.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+    suite.app.update_block(next_block);
+
+    let value_at = |height| suite.contract.query_value_at_height(&suite.app, height).unwrap().value;
+    assert_eq!([value_at(start), value_at(start + 1), value_at(start + 2)], [1, 3, 3]);
+    suite.contract.query_value_at_height(&suite.app, start - 1).unwrap_err();
+}
+
 #[test]
 fn withdraw() {
-    let owner = Addr::unchecked("owner");
     let sender1 = Addr::unchecked("sender1");
     let sender2 = Addr::unchecked("sender2");
-    let mut app = App::new( |router, _api, storage| {
-        router
-            .bank
-            .init_balance(storage, &sender1, coins(10, "atom"))
-            .unwrap();
-        router
-            .bank
-            .init_balance(storage, &sender2, coins(5, "atom"))
-            .unwrap();
-    });
-    let contract_id = app.store_code(counting_contract());
-    let contract = CountingContract::instantiate(
-        &mut app, 
-        contract_id, 
-        &owner, 
-        None,
-        "Counting Contract",
-        Coin::new(10, "atom")
-        )
-    .unwrap();
+    let mut suite = SuiteBuilder::new()
+        .with_funds("sender1", &coins(10, "atom"))
+        .with_funds("sender2", &coins(5, "atom"))
+        .build();
+    let owner = suite.owner.clone();
 
-    contract.donate(&mut app, &sender1, &coins(10, "atom")).unwrap();
-    contract.donate(&mut app, &sender2, &coins(5, "atom")).unwrap();
+    suite.contract.donate(&mut suite.app, &sender1, &coins(10, "atom")).unwrap();
+    suite.contract.donate(&mut suite.app, &sender2, &coins(5, "atom")).unwrap();
 
-    contract.withdraw(&mut app, &owner).unwrap();
+    suite.contract.withdraw(&mut suite.app, &owner).unwrap();
     assert_eq!(
-        app.wrap().query_all_balances(contract.addr()).unwrap(),
+        suite.app.wrap().query_all_balances(suite.contract.addr()).unwrap(),
         vec![]
     );
-    assert_eq!(app.wrap().query_all_balances(sender1).unwrap(), vec![]);
-    assert_eq!(app.wrap().query_all_balances(sender2).unwrap(), vec![]);
+    assert_eq!(suite.app.wrap().query_all_balances(&owner).unwrap(), coins(15, "atom"));
+    assert_eq!(suite.app.wrap().query_all_balances(sender1).unwrap(), vec![]);
+    assert_eq!(suite.app.wrap().query_all_balances(sender2).unwrap(), vec![]);
 }
+
+#[test]
+fn custom_min_donation() {
+    let sender = Addr::unchecked("sender");
+    let mut suite = SuiteBuilder::new()
+        .with_min_donation(Coin::new(5, "btc"))
+        .with_funds("sender", &[Coin::new(10, "atom"), Coin::new(5, "btc")])
+        .build();
+
+    suite.contract.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+    suite.contract.donate(&mut suite.app, &sender, &coins(5, "btc")).unwrap();
+
+    let resp = suite.contract.query_value(&suite.app).unwrap();
+    assert_eq!(resp.value, 1);
+}
+
 #[test]
 fn migration() {
-    let owner = Addr::unchecked("owner");
-    let admin = Addr::unchecked("admin");
     let sender = Addr::unchecked("sender");
+    let mut suite = SuiteBuilder::new()
+        .with_admin("admin")
+        .with_funds("sender", &coins(10, "atom"))
+        .with_legacy_v0_1()
+        .build();
+
+    suite
+        .legacy_contract
+        .as_ref()
+        .unwrap()
+        .donate(&mut suite.app, &sender, &coins(10, "atom"))
+        .unwrap();
+
+    suite.migrate().unwrap();
+
+    let resp = suite.contract.query_value(&suite.app).unwrap();
+    assert_eq!(resp.value, 1);
+
+    let state = COUNTERS.query(&suite.app.wrap(), suite.contract.addr().clone(), DEFAULT_COUNTER).unwrap();
+    assert_eq!(state, Some(CounterState {
+        counter: 1,
+        minimal_donation: Coin::new(10, "atom"),
+        owner: suite.owner.clone(),
+        balance: coins(10, "atom"),
+    }));
+
+    //history starts with the migration
+    let height = suite.app.block_info().height;
+    assert_eq!(suite.contract.query_value_at_height(&suite.app, height).unwrap().value, 1);
+    suite.contract.query_value_at_height(&suite.app, height - 1).unwrap_err();
+
+    let log = suite.contract.query_audit_log(&suite.app, None, None).unwrap().entries;
+    assert_eq!(log.len(), 1);
+    assert_eq!((log[0].kind, &log[0].actor), (AuditKind::Migrate, &None));
+}
+
+#[test]
+#[cfg(feature = "matching")]
+fn matching_pool() {
+    let alice = Addr::unchecked("alice");
+    let generous = Addr::unchecked("generous");
+    let frugal = Addr::unchecked("frugal");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &coins(35, "atom"))
+        .with_funds("generous", &coins(30, "atom"))
+        .with_funds("frugal", &[Coin::new(10, "atom"), Coin::new(5, "btc")])
+        .build();
+    let owner = suite.owner.clone();
+    let contract = &suite.contract;
+
+    contract.fund_matching(&mut suite.app, &generous, Decimal::one(), 10, &coins(30, "atom")).unwrap();
+    contract.fund_matching(&mut suite.app, &frugal, Decimal::percent(50), 100, &coins(10, "atom")).unwrap();
+    let err = contract.fund_matching(&mut suite.app, &frugal, Decimal::one(), 10, &coins(5, "btc")).unwrap_err();
+    assert_eq!(err, ContractError::MatchingDenomMismatch { denom: "atom".to_owned() });
+    let err = contract.fund_matching(&mut suite.app, &frugal, Decimal::zero(), 10, &coins(5, "btc")).unwrap_err();
+    assert_eq!(err, ContractError::InvalidMatchingRatio { max: MAX_MATCHING_RATIO });
+    let err = contract.fund_matching(&mut suite.app, &frugal, Decimal::one(), 10, &[]).unwrap_err();
+    assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+
+    //only qualifying donations are matched, each sponsor up to its cap and deposit
+    contract.donate(&mut suite.app, &alice, &coins(10, "atom")).unwrap();
+    contract.donate(&mut suite.app, &alice, &coins(5, "atom")).unwrap();
+    contract.donate(&mut suite.app, &alice, &coins(20, "atom")).unwrap();
+    let counter = contract.query_counters(&suite.app, None, None).unwrap().counters;
+    assert_eq!(counter[0].balance, coins(65, "atom"));
+
+    let matching = contract.query_matching(&suite.app).unwrap();
+    let sponsors: Vec<_> = matching
+        .sponsors
+        .iter()
+        .map(|sponsor| (sponsor.sponsor.as_str(), sponsor.matched.amount.u128(), sponsor.remaining.amount.u128()))
+        .collect();
+    assert_eq!(sponsors, [("frugal", 10, 0), ("generous", 20, 10)]);
+    assert_eq!(matching.remaining, coins(10, "atom"));
+
+    let err = contract.close_matching(&mut suite.app, &alice).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: owner.to_string() });
+    contract.close_matching(&mut suite.app, &owner).unwrap();
+    assert!(contract.query_matching(&suite.app).unwrap().remaining.is_empty());
+    assert_eq!(suite.app.wrap().query_all_balances(&generous).unwrap(), coins(10, "atom"));
+
+    contract.withdraw(&mut suite.app, &owner).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&owner).unwrap(), coins(65, "atom"));
+    assert!(suite.app.wrap().query_all_balances(contract.addr()).unwrap().is_empty());
+}
+
+#[test]
+#[cfg(feature = "matching")]
+fn matching_limits() {
+    let whale = Addr::unchecked("whale");
+    let sponsors: Vec<_> = (0..=MAX_ACTIVE_SPONSORS).map(|i| Addr::unchecked(format!("sponsor{i:02}"))).collect();
+    let mut builder = SuiteBuilder::new().with_funds("whale", &coins(u128::MAX / 2, "atom"));
+    for sponsor in &sponsors {
+        builder = builder.with_funds(sponsor.as_str(), &coins(200, "atom"));
+    }
+    let mut suite = builder.build();
+    let owner = suite.owner.clone();
+    let contract = &suite.contract;
+
+    let too_generous = MAX_MATCHING_RATIO + Decimal::percent(1);
+    let err = contract.fund_matching(&mut suite.app, &sponsors[0], too_generous, 50, &coins(100, "atom")).unwrap_err();
+    assert_eq!(err, ContractError::InvalidMatchingRatio { max: MAX_MATCHING_RATIO });
+
+    //donation times ratio overflows Uint128, which still matches only up to the cap
+    contract.fund_matching(&mut suite.app, &sponsors[0], MAX_MATCHING_RATIO, 50, &coins(100, "atom")).unwrap();
+    contract.donate(&mut suite.app, &whale, &coins(u128::MAX / 4, "atom")).unwrap();
+    let matching = contract.query_matching(&suite.app).unwrap();
+    assert_eq!((matching.sponsors[0].matched.amount.u128(), matching.remaining.clone()), (50, coins(50, "atom")));
+
+    for sponsor in &sponsors[1..MAX_ACTIVE_SPONSORS as usize] {
+        contract.fund_matching(&mut suite.app, sponsor, Decimal::one(), 1, &coins(100, "atom")).unwrap();
+    }
+    let last = &sponsors[MAX_ACTIVE_SPONSORS as usize];
+    let err = contract.fund_matching(&mut suite.app, last, Decimal::one(), 1, &coins(100, "atom")).unwrap_err();
+    assert_eq!(err, ContractError::TooManySponsors { max: MAX_ACTIVE_SPONSORS });
+    //active sponsors can still top up
+    contract.fund_matching(&mut suite.app, &sponsors[1], Decimal::one(), 1, &coins(100, "atom")).unwrap();
+
+    //closing frees every slot and closed pools match nothing
+    contract.close_matching(&mut suite.app, &owner).unwrap();
+    contract.donate(&mut suite.app, &whale, &coins(100, "atom")).unwrap();
+    let matching = contract.query_matching(&suite.app).unwrap();
+    assert!(matching.remaining.is_empty());
+    assert_eq!(matching.sponsors[0].matched.amount.u128(), 50);
+    contract.fund_matching(&mut suite.app, last, Decimal::one(), 1, &coins(100, "atom")).unwrap();
+}
+
+#[test]
+#[cfg(feature = "rounds")]
+fn quadratic_funding_round() {
+    let admin = Addr::unchecked("admin");
+    let sponsor = Addr::unchecked("sponsor");
+    let first = Addr::unchecked("first");
+    let second = Addr::unchecked("second");
+    let mut suite = SuiteBuilder::new()
+        .with_owner("admin")
+        .with_funds("sponsor", &coins(100, "atom"))
+        .with_funds("first", &coins(13, "atom"))
+        .with_funds("second", &coins(5, "atom"))
+        .build();
+    let contract = &suite.contract;
+    let end_time = suite.app.block_info().time.plus_seconds(100);
+    let round_id = || "round".to_owned();
+    let donate = |project: &str| ExecuteMsg::DonateToProject { round_id: round_id(), project_id: project.to_owned() };
+
+    let create = |denom: &str| ExecuteMsg::CreateRound { id: round_id(), denom: denom.to_owned(), end_time };
+    let err = contract.execute(&mut suite.app, &sponsor, &create("atom"), &[]).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: admin.to_string() });
+    for denom in [String::new(), "a".repeat(129)] {
+        let err = contract.execute(&mut suite.app, &admin, &create(&denom), &[]).unwrap_err();
+        assert_eq!(err, ContractError::InvalidDenom { denom });
+    }
+    contract.execute(&mut suite.app, &admin, &create("atom"), &[]).unwrap();
+    for (project, owner) in [("art", "artist"), ("bridge", "builder")] {
+        let msg = ExecuteMsg::RegisterProject { round_id: round_id(), project_id: project.to_owned(), owner: owner.to_owned() };
+        let err = contract.execute(&mut suite.app, &sponsor, &msg, &[]).unwrap_err();
+        assert_eq!(err, ContractError::Unauthorized { owner: admin.to_string() });
+        contract.execute(&mut suite.app, &admin, &msg, &[]).unwrap();
+    }
+    contract.execute(&mut suite.app, &sponsor, &ExecuteMsg::FundRound { round_id: round_id() }, &coins(100, "atom")).unwrap();
+
+    //two donors giving 4 each beat a single donor giving 9
+    contract.execute(&mut suite.app, &first, &donate("art"), &coins(2, "atom")).unwrap();
+    contract.execute(&mut suite.app, &first, &donate("art"), &coins(2, "atom")).unwrap();
+    contract.execute(&mut suite.app, &second, &donate("art"), &coins(4, "atom")).unwrap();
+    contract.execute(&mut suite.app, &first, &donate("bridge"), &coins(9, "atom")).unwrap();
+    contract.execute(&mut suite.app, &second, &donate("bridge"), &coins(1, "atom")).unwrap();
+
+    let round = contract.query_round(&suite.app, "round").unwrap();
+    let projects: Vec<_> = round
+        .projects
+        .iter()
+        .map(|project| (project.id.as_str(), project.contributed.u128(), project.donors, project.matching.u128()))
+        .collect();
+    assert_eq!(projects, [("art", 8, 2, 57), ("bridge", 10, 2, 42)]);
+
+    let finalize = ExecuteMsg::FinalizeRound { round_id: round_id() };
+    let err = contract.execute(&mut suite.app, &admin, &finalize, &[]).unwrap_err();
+    assert_eq!(err, ContractError::RoundNotEnded { id: round_id() });
+    let withdraw = ExecuteMsg::WithdrawProject { round_id: round_id(), project_id: "art".to_owned() };
+    let err = contract.execute(&mut suite.app, &Addr::unchecked("artist"), &withdraw, &[]).unwrap_err();
+    assert_eq!(err, ContractError::RoundNotFinalized { id: round_id() });
+
+    suite.app.update_block(|block| block.time = end_time);
+    let err = contract.execute(&mut suite.app, &second, &donate("art"), &[]).unwrap_err();
+    assert_eq!(err, ContractError::RoundEnded { id: round_id() });
+    contract.execute(&mut suite.app, &sponsor, &finalize, &[]).unwrap();
+    let err = contract.execute(&mut suite.app, &sponsor, &finalize, &[]).unwrap_err();
+    assert_eq!(err, ContractError::RoundFinalized { id: round_id() });
+
+    contract.execute(&mut suite.app, &Addr::unchecked("artist"), &withdraw, &[]).unwrap();
+    let err = contract.execute(&mut suite.app, &Addr::unchecked("artist"), &withdraw, &[]).unwrap_err();
+    assert_eq!(err, ContractError::NothingToWithdraw { round_id: round_id(), project_id: "art".to_owned() });
+    let withdraw = ExecuteMsg::WithdrawProject { round_id: round_id(), project_id: "bridge".to_owned() };
+    contract.execute(&mut suite.app, &Addr::unchecked("builder"), &withdraw, &[]).unwrap();
+
+    let balance = |addr: &str| suite.app.wrap().query_balance(addr, "atom").unwrap().amount.u128();
+    assert_eq!(balance("artist"), 65);
+    assert_eq!(balance("builder"), 52);
+    //rounding leftover
+    assert_eq!(balance("admin"), 1);
+    assert_eq!(balance(contract.addr().as_str()), 0);
+
+    let entries = contract.query_audit_log(&suite.app, None, None).unwrap().entries;
+    let kinds: Vec<_> = entries.iter().map(|entry| (entry.kind, entry.target.as_deref().unwrap())).collect();
+    assert_eq!(kinds, [
+        (AuditKind::RegisterProject, "round/art"),
+        (AuditKind::RegisterProject, "round/bridge"),
+        (AuditKind::FinalizeRound, "round"),
+        (AuditKind::WithdrawProject, "round/art"),
+        (AuditKind::WithdrawProject, "round/bridge"),
+    ]);
+    assert_eq!((entries[3].before.as_str(), entries[3].after.as_str()), (r#""65""#, r#""0""#));
+
+    let dump = decoder::decode(&suite.app.dump_wasm_raw(contract.addr()));
+    assert_eq!(dump.flagged().count(), 0);
+    assert!(dump.entries.iter().any(|entry| matches!(&entry.value, Some(KnownValue::Project(project)) if project.withdrawn)));
+}
+
+#[test]
+#[cfg(feature = "rounds")]
+fn round_with_18_decimal_amounts() {
+    const EVMOS: u128 = 1_000_000_000_000_000_000;
+    let admin = Addr::unchecked("admin");
+    let mut suite = SuiteBuilder::new()
+        .with_owner("admin")
+        .with_funds("sponsor", &coins(1_000 * EVMOS, "aevmos"))
+        .with_funds("first", &coins(200 * EVMOS, "aevmos"))
+        .with_funds("second", &coins(200 * EVMOS, "aevmos"))
+        .build();
+    let contract = &suite.contract;
+    let end_time = suite.app.block_info().time.plus_seconds(100);
+    let round_id = || "round".to_owned();
+
+    let create = ExecuteMsg::CreateRound { id: round_id(), denom: "aevmos".to_owned(), end_time };
+    contract.execute(&mut suite.app, &admin, &create, &[]).unwrap();
+    let register = ExecuteMsg::RegisterProject { round_id: round_id(), project_id: "art".to_owned(), owner: "artist".to_owned() };
+    contract.execute(&mut suite.app, &admin, &register, &[]).unwrap();
+    let fund = ExecuteMsg::FundRound { round_id: round_id() };
+    contract.execute(&mut suite.app, &Addr::unchecked("sponsor"), &fund, &coins(1_000 * EVMOS, "aevmos")).unwrap();
+    let donate = ExecuteMsg::DonateToProject { round_id: round_id(), project_id: "art".to_owned() };
+    for donor in ["first", "second"] {
+        contract.execute(&mut suite.app, &Addr::unchecked(donor), &donate, &coins(200 * EVMOS, "aevmos")).unwrap();
+    }
+    //the only project takes the whole pool
+    let round = contract.query_round(&suite.app, "round").unwrap();
+    assert_eq!(round.projects[0].matching.u128(), 1_000 * EVMOS);
+
+    suite.app.update_block(|block| block.time = end_time);
+    contract.execute(&mut suite.app, &admin, &ExecuteMsg::FinalizeRound { round_id: round_id() }, &[]).unwrap();
+    let withdraw = ExecuteMsg::WithdrawProject { round_id: round_id(), project_id: "art".to_owned() };
+    contract.execute(&mut suite.app, &Addr::unchecked("artist"), &withdraw, &[]).unwrap();
+    assert_eq!(suite.app.wrap().query_balance("artist", "aevmos").unwrap().amount.u128(), 1_400 * EVMOS);
+}
+
+#[test]
+fn donor_tiers() {
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &coins(50, "atom"))
+        .with_funds("bob", &coins(2, "btc"))
+        .build();
+    let owner = suite.owner.clone();
+    let contract = &suite.contract;
+    let thresholds = |bronze: u128, silver: u128, gold: u128| TierThresholds {
+        bronze: Uint128::new(bronze),
+        silver: Uint128::new(silver),
+        gold: Uint128::new(gold),
+    };
+    let set = |denom: &str, thresholds| ExecuteMsg::SetTierThresholds {
+        counter_id: None,
+        denom: denom.to_owned(),
+        thresholds: Some(thresholds),
+    };
+
+    let err = contract.execute(&mut suite.app, &alice, &set("atom", thresholds(10, 20, 50)), &[]).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: owner.to_string() });
+    let err = contract.execute(&mut suite.app, &owner, &set("atom", thresholds(10, 10, 50)), &[]).unwrap_err();
+    assert_eq!(err, ContractError::InvalidTierThresholds {});
+    contract.execute(&mut suite.app, &owner, &set("atom", thresholds(10, 20, 50)), &[]).unwrap();
+    contract.execute(&mut suite.app, &owner, &set("btc", thresholds(1, 2, 3)), &[]).unwrap();
+
+    let donate = ExecuteMsg::Donate { counter_id: None };
+    let mut tier_changes = |sender: &Addr, funds: Vec<Coin>| -> Vec<String> {
+        let resp = contract.execute(&mut suite.app, sender, &donate, &funds).unwrap();
+        resp.events
+            .iter()
+            .filter(|event| event.ty == "wasm-tier_changed")
+            .flat_map(|event| &event.attributes)
+            .filter(|attr| attr.key == "tier")
+            .map(|attr| attr.value.clone())
+            .collect()
+    };
+    assert!(tier_changes(&alice, coins(5, "atom")).is_empty());
+    assert_eq!(tier_changes(&alice, coins(5, "atom")), ["bronze"]);
+    assert_eq!(tier_changes(&alice, coins(40, "atom")), ["gold"]);
+    assert_eq!(tier_changes(&bob, coins(2, "btc")), ["silver"]);
+
+    let resp = contract.query_donor_tier(&suite.app, &alice).unwrap();
+    assert_eq!(resp.tier, Some(Tier::Gold));
+    assert_eq!(resp.donated, coins(50, "atom"));
+    assert_eq!(contract.query_donors_by_tier(&suite.app, Tier::Gold).unwrap(), [alice]);
+    assert_eq!(contract.query_donors_by_tier(&suite.app, Tier::Silver).unwrap(), [bob]);
+    assert!(contract.query_donors_by_tier(&suite.app, Tier::Bronze).unwrap().is_empty());
+}
+
+#[test]
+fn donation_grace_period() {
+    let alice = Addr::unchecked("alice");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &coins(30, "atom"))
+        .with_grace_period(60)
+        .build();
+    let owner = suite.owner.clone();
+    let contract = &suite.contract;
+    let donate = ExecuteMsg::Donate { counter_id: None };
+    let cancel = |id| ExecuteMsg::CancelDonation { id };
+
+    let mut donation_ids = vec![];
+    for amount in [10, 15] {
+        let resp = contract.execute(&mut suite.app, &alice, &donate, &coins(amount, "atom")).unwrap();
+        let attr = resp.custom_attrs(1).iter().find(|attr| attr.key == "donation_id").unwrap();
+        donation_ids.push(attr.value.parse::<u64>().unwrap());
+    }
+    assert_eq!(donation_ids, [0, 1]);
+    assert_eq!(contract.query_value(&suite.app).unwrap().value, 2);
+    let err = contract.execute(&mut suite.app, &alice, &donate, &[]).unwrap_err();
+    assert_eq!(err, ContractError::Payment(PaymentError::NoFunds {}));
+
+    let err = contract.execute(&mut suite.app, &owner, &cancel(0), &[]).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: alice.to_string() });
+    contract.execute(&mut suite.app, &alice, &cancel(0), &[]).unwrap();
+    assert_eq!(contract.query_value(&suite.app).unwrap().value, 1);
+    assert_eq!(suite.app.wrap().query_all_balances(&alice).unwrap(), coins(15, "atom"));
+    let err = contract.execute(&mut suite.app, &alice, &cancel(0), &[]).unwrap_err();
+    assert_eq!(err, ContractError::DonationNotFound { id: 0 });
+
+    //still cancellable, so it stays with the contract
+    contract.withdraw(&mut suite.app, &owner).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&owner).unwrap(), vec![]);
+
+    suite.app.update_block(|block| block.time = block.time.plus_seconds(60));
+    let err = contract.execute(&mut suite.app, &alice, &cancel(1), &[]).unwrap_err();
+    assert_eq!(err, ContractError::GracePeriodEnded { id: 1 });
+    contract.withdraw(&mut suite.app, &owner).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&owner).unwrap(), coins(15, "atom"));
+    assert_eq!(suite.app.wrap().query_all_balances(contract.addr()).unwrap(), vec![]);
+
+    //only the donations whose grace period has passed are withdrawn
+    contract.execute(&mut suite.app, &alice, &donate, &coins(2, "atom")).unwrap();
+    suite.app.update_block(|block| block.time = block.time.plus_seconds(30));
+    contract.execute(&mut suite.app, &alice, &donate, &coins(3, "atom")).unwrap();
+    suite.app.update_block(|block| block.time = block.time.plus_seconds(30));
+    contract.withdraw(&mut suite.app, &owner).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&owner).unwrap(), coins(17, "atom"));
+    assert_eq!(suite.app.wrap().query_all_balances(contract.addr()).unwrap(), coins(3, "atom"));
+}
+
+#[test]
+fn donate_for_beneficiary() {
+    let alice = Addr::unchecked("alice");
+    let processor = Addr::unchecked("processor");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("processor", &coins(20, "atom"))
+        .with_grace_period(60)
+        .build();
+    let contract = &suite.contract;
+
+    let donate_for = |beneficiary: &str| ExecuteMsg::DonateFor {
+        counter_id: None,
+        beneficiary: beneficiary.to_owned(),
+    };
+    let err = contract.execute(&mut suite.app, &processor, &donate_for(""), &coins(10, "atom")).unwrap_err();
+    assert!(matches!(err, ContractError::Std(_)));
+
+    let resp = contract
+        .execute(&mut suite.app, &processor, &donate_for(alice.as_str()), &coins(10, "atom"))
+        .unwrap();
+    let attrs = resp.custom_attrs(1);
+    assert!(attrs.iter().any(|attr| attr.key == "sender" && attr.value == processor.as_str()));
+    assert!(attrs.iter().any(|attr| attr.key == "beneficiary" && attr.value == alice.as_str()));
+    assert_eq!(contract.query_value(&suite.app).unwrap().value, 1);
+    assert_eq!(contract.query_donor_tier(&suite.app, &alice).unwrap().donated, coins(10, "atom"));
+    assert!(contract.query_donor_tier(&suite.app, &processor).unwrap().donated.is_empty());
+
+    //the refund goes to the beneficiary
+    let cancel = ExecuteMsg::CancelDonation { id: 0 };
+    let err = contract.execute(&mut suite.app, &processor, &cancel, &[]).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: alice.to_string() });
+    contract.execute(&mut suite.app, &alice, &cancel, &[]).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&alice).unwrap(), coins(10, "atom"));
+    assert_eq!(suite.app.wrap().query_all_balances(&processor).unwrap(), coins(10, "atom"));
+}
+
+#[test]
+fn platform_fee() {
+    let alice = Addr::unchecked("alice");
+    let collector = Addr::unchecked("collector");
+    let fee = |mode| FeeConfig {
+        bps: 500,
+        collector: collector.to_string(),
+        admin: "fee_admin".to_owned(),
+        mode,
+    };
+
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &coins(200, "atom"))
+        .with_fee(fee(FeeMode::Donation))
+        .build();
+    let owner = suite.owner.clone();
+    suite.contract.donate(&mut suite.app, &alice, &coins(100, "atom")).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&collector).unwrap(), coins(5, "atom"));
+    //below 1 atom of fee
+    suite.contract.donate(&mut suite.app, &alice, &coins(19, "atom")).unwrap();
+    suite.contract.withdraw(&mut suite.app, &owner).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&owner).unwrap(), coins(114, "atom"));
+
+    let update = ExecuteMsg::UpdateFee { fee: fee(FeeMode::Withdraw) };
+    let err = suite.contract.execute(&mut suite.app, &owner, &update, &[]).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: "fee_admin".to_owned() });
+    let fee_admin = Addr::unchecked("fee_admin");
+    let invalid = ExecuteMsg::UpdateFee { fee: FeeConfig { bps: 10_001, ..fee(FeeMode::Withdraw) } };
+    let err = suite.contract.execute(&mut suite.app, &fee_admin, &invalid, &[]).unwrap_err();
+    assert_eq!(err, ContractError::InvalidFeeBps { bps: 10_001 });
+    suite.contract.execute(&mut suite.app, &fee_admin, &update, &[]).unwrap();
+
+    suite.contract.donate(&mut suite.app, &alice, &coins(60, "atom")).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&collector).unwrap(), coins(5, "atom"));
+    suite.contract.withdraw(&mut suite.app, &owner).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&owner).unwrap(), coins(171, "atom"));
+
+    let report = suite.contract.query_fee_report(&suite.app).unwrap();
+    assert_eq!(report.fee, Some(fee(FeeMode::Withdraw)));
+    assert_eq!(report.collected, coins(8, "atom"));
+}
+
+#[test]
+fn fee_set_on_migrate() {
+    let fee = FeeConfig {
+        bps: 500,
+        collector: "collector".to_owned(),
+        admin: "fee_admin".to_owned(),
+        mode: FeeMode::Donation,
+    };
+    let mut suite = SuiteBuilder::new().with_admin("admin").build();
+    let admin = suite.admin.clone().unwrap();
+    let addr = suite.contract.addr().clone();
+
+    let invalid = MigrateMsg { fee: Some(FeeConfig { bps: 10_001, ..fee.clone() }) };
+    CountingContract::migrate_with_msg(&mut suite.app, &admin, &addr, suite.code_id, &invalid).unwrap_err();
+    assert_eq!(suite.contract.query_fee_report(&suite.app).unwrap().fee, None);
+
+    let msg = MigrateMsg { fee: Some(fee.clone()) };
+    CountingContract::migrate_with_msg(&mut suite.app, &admin, &addr, suite.code_id, &msg).unwrap();
+    assert_eq!(suite.contract.query_fee_report(&suite.app).unwrap().fee, Some(fee.clone()));
+
+    //migrating without a fee keeps the current one
+    suite.migrate().unwrap();
+    assert_eq!(suite.contract.query_fee_report(&suite.app).unwrap().fee, Some(fee));
+
+    let log = suite.contract.query_audit_log(&suite.app, None, None).unwrap().entries;
+    let kinds: Vec<_> = log.iter().map(|entry| (entry.kind, entry.actor.clone())).collect();
+    assert_eq!(kinds, [
+        (AuditKind::Migrate, None),
+        (AuditKind::UpdateFee, None),
+        (AuditKind::Migrate, None),
+    ]);
+    assert_eq!(log[1].before, "null");
+}
+
+#[test]
+fn audit_log() {
+    let alice = Addr::unchecked("alice");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &coins(10, "atom"))
+        .build();
+    let owner = suite.owner.clone();
+    let contract = &suite.contract;
 
-    let mut app = App::new( |router, _api, storage| {
+    let thresholds = TierThresholds { bronze: Uint128::new(1), silver: Uint128::new(2), gold: Uint128::new(3) };
+    let set = ExecuteMsg::SetTierThresholds {
+        counter_id: None,
+        denom: "atom".to_owned(),
+        thresholds: Some(thresholds),
+    };
+    contract.execute(&mut suite.app, &owner, &set, &[]).unwrap();
+    contract.donate(&mut suite.app, &alice, &coins(10, "atom")).unwrap();
+    contract.withdraw(&mut suite.app, &owner).unwrap();
+
+    let log = contract.query_audit_log(&suite.app, None, None).unwrap().entries;
+    let kinds: Vec<_> = log.iter().map(|entry| entry.kind).collect();
+    assert_eq!(kinds, [AuditKind::SetTierThresholds, AuditKind::Withdraw]);
+    assert_eq!(log[1].actor, Some(owner.clone()));
+    assert_eq!(log[1].target.as_deref(), Some(DEFAULT_COUNTER));
+    assert_eq!(log[1].before, r#"[{"denom":"atom","amount":"10"}]"#);
+    assert_eq!(log[1].after, "[]");
+    assert_eq!(audit::verify(&log, None), Ok(()));
+
+    //pages verify against the last digest of the previous one
+    let page = contract.query_audit_log(&suite.app, Some(0), Some(1)).unwrap().entries;
+    assert_eq!(audit::verify(&page, Some(&log[0].digest)), Ok(()));
+    assert_eq!(audit::verify(&page, None), Err(1));
+
+    let mut tampered = log.clone();
+    tampered[0].before = "{}".to_owned();
+    assert_eq!(audit::verify(&tampered, None), Err(0));
+}
+
+#[test]
+fn attested_donations() {
+    let alice = Addr::unchecked("alice");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &coins(10, "atom"))
+        .build();
+    let owner = suite.owner.clone();
+    let contract = &suite.contract;
+
+    let key = SigningKey::from_slice(&[7; 32]).unwrap();
+    let pubkey = Binary::from(key.verifying_key().to_encoded_point(true).as_bytes());
+    let set = |pubkey| ExecuteMsg::SetAttestor { counter_id: None, name: "kyc".to_owned(), pubkey: Some(pubkey) };
+    let err = contract.execute(&mut suite.app, &alice, &set(pubkey.clone()), &[]).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: owner.to_string() });
+    let err = contract.execute(&mut suite.app, &owner, &set(Binary::from([2; 32])), &[]).unwrap_err();
+    assert_eq!(err, ContractError::InvalidPubkey {});
+    contract.execute(&mut suite.app, &owner, &set(pubkey), &[]).unwrap();
+
+    let donate = |attestor: &str, donor: &str| {
+        let hash = attestation::digest(contract.addr().as_str(), DEFAULT_COUNTER, donor, "alice@example.com").unwrap();
+        let signature: Signature = key.sign_prehash(&hash).unwrap();
+        let attestation = Attestation {
+            attestor: attestor.to_owned(),
+            identity: "alice@example.com".to_owned(),
+            signature: Binary::from(&signature.to_bytes()[..]),
+        };
+        ExecuteMsg::DonateAttested { counter_id: None, attestation }
+    };
+    let err = contract.execute(&mut suite.app, &alice, &donate("kyc", "bob"), &coins(10, "atom")).unwrap_err();
+    assert_eq!(err, ContractError::InvalidAttestation {});
+    let err = contract.execute(&mut suite.app, &alice, &donate("other", "alice"), &coins(10, "atom")).unwrap_err();
+    assert_eq!(err, ContractError::AttestorNotFound { name: "other".to_owned() });
+    contract.execute(&mut suite.app, &alice, &donate("kyc", "alice"), &coins(10, "atom")).unwrap();
+
+    assert_eq!(contract.query_value(&suite.app).unwrap().value, 1);
+    let donors = contract.query_verified_donors(&suite.app).unwrap().donors;
+    assert_eq!(donors.len(), 1);
+    assert_eq!((&donors[0].address, donors[0].attestor.as_str()), (&alice, "kyc"));
+    let hash = attestation::digest(contract.addr().as_str(), DEFAULT_COUNTER, "alice", "alice@example.com").unwrap();
+    assert_eq!(donors[0].attestation_hash, hash);
+}
+
+#[test]
+#[cfg(feature = "permits")]
+fn withdraw_permits() {
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let relayer = Addr::unchecked("relayer");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &coins(20, "atom"))
+        .build();
+    let owner = suite.owner.clone();
+    let contract = &suite.contract;
+    contract.donate(&mut suite.app, &alice, &coins(20, "atom")).unwrap();
+    let expires = suite.app.block_info().time.plus_seconds(60);
+
+    let permit = |nonce, amount| WithdrawPermit {
+        counter_id: None,
+        receiver: bob.to_string(),
+        amount: coins(amount, "atom"),
+        nonce,
+        expires,
+    };
+    let secp = SigningKey::from_slice(&[7; 32]).unwrap();
+    let secp_sign = |permit: WithdrawPermit| {
+        let hash = permit::digest(contract.addr().as_str(), &permit).unwrap();
+        let signature: Signature = secp.sign_prehash(&hash).unwrap();
+        ExecuteMsg::WithdrawWithPermit { permit, signature: Binary::from(&signature.to_bytes()[..]) }
+    };
+    let err = contract.execute(&mut suite.app, &relayer, &secp_sign(permit(0, 5)), &[]).unwrap_err();
+    assert_eq!(err, ContractError::OwnerPubkeyNotSet { id: DEFAULT_COUNTER.to_owned() });
+
+    let set = |pubkey| ExecuteMsg::SetOwnerPubkey { counter_id: None, pubkey: Some(pubkey) };
+    let secp_pubkey = OwnerPubkey::Secp256k1(Binary::from(secp.verifying_key().to_encoded_point(true).as_bytes()));
+    let err = contract.execute(&mut suite.app, &relayer, &set(secp_pubkey.clone()), &[]).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: owner.to_string() });
+    let err = contract.execute(&mut suite.app, &owner, &set(OwnerPubkey::Ed25519(Binary::from([1; 33]))), &[]).unwrap_err();
+    assert_eq!(err, ContractError::InvalidOwnerPubkey {});
+    contract.execute(&mut suite.app, &owner, &set(secp_pubkey), &[]).unwrap();
+
+    contract.execute(&mut suite.app, &relayer, &secp_sign(permit(0, 5)), &[]).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&bob).unwrap(), coins(5, "atom"));
+    let err = contract.execute(&mut suite.app, &relayer, &secp_sign(permit(0, 5)), &[]).unwrap_err();
+    assert_eq!(err, ContractError::PermitUsed { nonce: 0 });
+    let err = contract.execute(&mut suite.app, &relayer, &secp_sign(permit(1, 16)), &[]).unwrap_err();
+    assert_eq!(err, ContractError::InsufficientBalance { amount: Coin::new(16, "atom") });
+    //tampering with a signed permit breaks the signature
+    let mut tampered = secp_sign(permit(1, 5));
+    if let ExecuteMsg::WithdrawWithPermit { permit, .. } = &mut tampered {
+        permit.receiver = relayer.to_string();
+    }
+    let err = contract.execute(&mut suite.app, &relayer, &tampered, &[]).unwrap_err();
+    assert_eq!(err, ContractError::InvalidPermit {});
+
+    let ed25519 = Ed25519SigningKey::from([9; 32]);
+    let ed25519_pubkey = <[u8; 32]>::from(Ed25519VerificationKey::from(&ed25519));
+    contract.execute(&mut suite.app, &owner, &set(OwnerPubkey::Ed25519(Binary::from(ed25519_pubkey))), &[]).unwrap();
+    let ed25519_sign = |permit: WithdrawPermit| {
+        let hash = permit::digest(contract.addr().as_str(), &permit).unwrap();
+        let signature = <[u8; 64]>::from(ed25519.sign(hash.as_slice()));
+        ExecuteMsg::WithdrawWithPermit { permit, signature: Binary::from(signature) }
+    };
+    let err = contract.execute(&mut suite.app, &relayer, &secp_sign(permit(1, 5)), &[]).unwrap_err();
+    assert_eq!(err, ContractError::InvalidPermit {});
+    contract.execute(&mut suite.app, &relayer, &ed25519_sign(permit(1, 15)), &[]).unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&bob).unwrap(), coins(20, "atom"));
+
+    suite.app.update_block(|block| block.time = block.time.plus_seconds(60));
+    let err = contract.execute(&mut suite.app, &relayer, &ed25519_sign(permit(2, 0)), &[]).unwrap_err();
+    assert_eq!(err, ContractError::PermitExpired { nonce: 2 });
+
+    let entries = contract.query_audit_log(&suite.app, None, None).unwrap().entries;
+    let last = entries.last().unwrap();
+    assert_eq!((last.kind, last.actor.as_ref()), (AuditKind::WithdrawWithPermit, Some(&owner)));
+}
+
+#[test]
+fn named_counters() {
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &[Coin::new(30, "atom"), Coin::new(5, "btc")])
+        .build();
+    let owner = suite.owner.clone();
+    let contract = &suite.contract;
+
+    contract.create_counter(&mut suite.app, &alice, "bobs", Coin::new(5, "btc"), Some(&bob)).unwrap();
+    let err = contract.create_counter(&mut suite.app, &bob, "bobs", Coin::new(1, "atom"), None).unwrap_err();
+    assert_eq!(err, ContractError::CounterExists { id: "bobs".to_owned() });
+    let err = contract.create_counter(&mut suite.app, &bob, "no spaces", Coin::new(1, "atom"), None).unwrap_err();
+    assert_eq!(err, ContractError::InvalidId { id: "no spaces".to_owned() });
+
+    contract.donate(&mut suite.app, &alice, &coins(10, "atom")).unwrap();
+    contract.donate_to(&mut suite.app, &alice, "bobs", &[Coin::new(20, "atom"), Coin::new(5, "btc")]).unwrap();
+    let err = contract.donate_to(&mut suite.app, &alice, "missing", &[]).unwrap_err();
+    assert_eq!(err, ContractError::CounterNotFound { id: "missing".to_owned() });
+
+    assert_eq!(contract.query_value(&suite.app).unwrap().value, 1);
+    assert_eq!(contract.query_counter_value(&suite.app, "bobs").unwrap().value, 1);
+
+    //each owner only gets what was donated to their own counter
+    let err = contract.withdraw_from(&mut suite.app, &owner, "bobs").unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: bob.to_string() });
+    contract.withdraw(&mut suite.app, &owner).unwrap();
+    contract.withdraw_from(&mut suite.app, &bob, "bobs").unwrap();
+    assert_eq!(suite.app.wrap().query_all_balances(&owner).unwrap(), coins(10, "atom"));
+    assert_eq!(
+        suite.app.wrap().query_all_balances(&bob).unwrap(),
+        [Coin::new(20, "atom"), Coin::new(5, "btc")]
+    );
+
+    let counters = contract.query_counters(&suite.app, None, Some(1)).unwrap().counters;
+    assert_eq!(counters.len(), 1);
+    assert_eq!(counters[0].id, "bobs");
+    assert_eq!(counters[0].owner, bob);
+    let counters = contract.query_counters(&suite.app, Some("bobs"), None).unwrap().counters;
+    let ids: Vec<_> = counters.iter().map(|counter| counter.id.as_str()).collect();
+    assert_eq!(ids, [DEFAULT_COUNTER]);
+    assert!(counters[0].balance.is_empty());
+}
+
+fn donate_and_withdraw_scenario<C: CountingApi>() {
+    let owner = Addr::unchecked("owner");
+    let sender = Addr::unchecked("sender");
+    let mut app = App::new(|router, _api, storage| {
         router
             .bank
-            .init_balance(storage, &sender, coins(10, "atom"))
+            .init_balance(storage, &sender, coins(20, "atom"))
             .unwrap();
     });
+    let code_id = C::store_code(&mut app);
+    let contract = C::instantiate(
+        &mut app,
+        code_id,
+        &owner,
+        Some(&owner),
+        "Counting Contract",
+        Coin::new(10, "atom"),
+    )
+    .unwrap();
 
-    let old_code_id = Counting_Contract_0_1_0::store_code(&mut app);
-    let new_code_id = CountingContract::store_code(&mut app);
+    contract.donate(&mut app, &sender, &coins(10, "atom")).unwrap();
+    contract.donate(&mut app, &sender, &coins(5, "atom")).unwrap();
+    assert_eq!(contract.query_value(&app).unwrap().value, 1, "{}", C::VERSION);
 
-    let contract = Counting_Contract_0_1_0::instantiate(
-        &mut app, 
-        old_code_id, 
-        &owner, 
-        Some(&admin),
-        "Counting Contract",
-        Coin::new(10, "atom")
-        )
+    let err = contract.withdraw(&mut app, &sender).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: owner.to_string() }, "{}", C::VERSION);
+
+    contract.withdraw(&mut app, &owner).unwrap();
+    assert_eq!(app.wrap().query_all_balances(&owner).unwrap(), coins(15, "atom"), "{}", C::VERSION);
+
+    //migrating to the code it already runs keeps the state
+    if contract.supports_migrate() {
+        contract.migrate(&mut app, &owner, code_id).unwrap();
+        assert_eq!(contract.query_value(&app).unwrap().value, 1, "{}", C::VERSION);
+    } else {
+        contract.migrate(&mut app, &owner, code_id).unwrap_err();
+    }
+
+    if contract.supports_reset() {
+        contract.reset(&mut app, &owner).unwrap();
+        assert_eq!(contract.query_value(&app).unwrap().value, 0, "{}", C::VERSION);
+    } else {
+        contract.reset(&mut app, &owner).unwrap_err();
+    }
+}
+
+#[test]
+fn donate_and_withdraw_every_version() {
+    donate_and_withdraw_scenario::<CountingContract_0_1_0>();
+    donate_and_withdraw_scenario::<CountingContract>();
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn donate_withdraw_invariants(legacy in any::<bool>(), ops in state_machine::ops_strategy(20)) {
+        state_machine::run(legacy, &ops)?;
+    }
+}
+
+#[test]
+fn query_config() {
+    let suite = SuiteBuilder::new()
+        .with_owner("owner")
+        .with_min_donation(Coin::new(5, "btc"))
+        .build();
+
+    let resp = suite.app.wrap().config(suite.contract.addr()).unwrap();
+    assert_eq!(resp, ConfigResp {
+        owner: Addr::unchecked("owner"),
+        minimal_donation: Coin::new(5, "btc"),
+    });
+}
+
+#[test]
+fn example_scenarios_pass() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+    for file in ["migration.toml", "withdraw.json"] {
+        let report = Scenario::load(&dir.join(file)).unwrap().run();
+        assert!(report.passed(), "{}", report);
+    }
+}
+
+#[test]
+fn scenario_reports_mismatches() {
+    let scenario = Scenario::from_json(r#"{
+        "steps": [
+            { "action": "instantiate", "version": "0.2", "sender": "owner",
+              "minimal_donation": { "denom": "atom", "amount": "10" } },
+            { "action": "withdraw", "sender": "alice" },
+            { "action": "query", "value": 1 }
+        ]
+    }"#).unwrap();
+
+    let report = scenario.run();
+    assert!(!report.passed());
+    assert!(report.steps[0].passed());
+    assert!(report.steps[1].failures[0].contains("Unauthorized"));
+    assert!(report.steps[2].failures[0].contains("expected: 1"));
+}
+
+#[test]
+fn donate_and_withdraw_transcript() {
+    let alice = Addr::unchecked("alice");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("alice", &[Coin::new(20, "atom"), Coin::new(5, "btc")])
+        .build();
+    let owner = suite.owner.clone();
+    let mut contract = RecordingContract::new(suite.contract);
+
+    contract.donate(&mut suite.app, &alice, &coins(10, "atom")).unwrap();
+    contract.donate(&mut suite.app, &alice, &[Coin::new(5, "atom"), Coin::new(5, "btc")]).unwrap();
+    contract.withdraw(&mut suite.app, &alice).unwrap_err();
+    contract.withdraw(&mut suite.app, &owner).unwrap();
+
+    contract.assert_golden("donate_and_withdraw");
+}
+
+#[test]
+fn migration_transcript() {
+    let alice = Addr::unchecked("alice");
+    let mut suite = SuiteBuilder::new()
+        .with_admin("admin")
+        .with_funds("alice", &coins(20, "atom"))
+        .with_legacy_v0_1()
+        .build();
+    let admin = suite.admin.clone().unwrap();
+    let code_id = suite.code_id;
+    let mut contract = RecordingContract::new(suite.contract);
+
+    contract.donate(&mut suite.app, &alice, &coins(10, "atom")).unwrap();
+    contract.migrate(&mut suite.app, &alice, code_id).unwrap_err();
+    contract.migrate(&mut suite.app, &admin, code_id).unwrap();
+    contract.donate(&mut suite.app, &alice, &coins(10, "atom")).unwrap();
+
+    contract.assert_golden("migration");
+}
+
+#[test]
+fn instantiate_msg_leaves_out_empty_fields() {
+    //builds with and without the export feature have to accept each other's messages
+    let msg = InstantiateMsg::new(Coin::new(10, "atom"));
+    let json = r#"{"counter":0,"minimal_donation":{"denom":"atom","amount":"10"}}"#;
+    assert_eq!(to_json_string(&msg).unwrap(), json);
+    assert_eq!(from_json::<InstantiateMsg>(json).unwrap(), msg);
+}
+
+#[test]
+#[cfg(feature = "export")]
+fn export_and_import_state() {
+    let sender = Addr::unchecked("sender");
+    let fee = FeeConfig {
+        bps: 1_000,
+        collector: "collector".to_owned(),
+        admin: "fee_admin".to_owned(),
+        mode: FeeMode::Donation,
+    };
+    let mut suite = SuiteBuilder::new()
+        .with_funds("sender", &coins(20, "atom"))
+        .with_fee(fee.clone())
+        .build();
+    let owner = suite.owner.clone();
+    let thresholds = TierThresholds { bronze: Uint128::new(5), silver: Uint128::new(15), gold: Uint128::new(30) };
+    let set_thresholds = ExecuteMsg::SetTierThresholds {
+        counter_id: None,
+        denom: "atom".to_owned(),
+        thresholds: Some(thresholds.clone()),
+    };
+    suite.contract.execute(&mut suite.app, &owner, &set_thresholds, &[]).unwrap();
+    let key = SigningKey::from_slice(&[7; 32]).unwrap();
+    let pubkey = Binary::from(key.verifying_key().to_encoded_point(true).as_bytes());
+    let set_attestor = ExecuteMsg::SetAttestor { counter_id: None, name: "kyc".to_owned(), pubkey: Some(pubkey.clone()) };
+    suite.contract.execute(&mut suite.app, &owner, &set_attestor, &[]).unwrap();
+    #[cfg(feature = "permits")]
+    {
+        let pubkey = OwnerPubkey::Ed25519(Binary::from([3; 32]));
+        let set = ExecuteMsg::SetOwnerPubkey { counter_id: None, pubkey: Some(pubkey) };
+        suite.contract.execute(&mut suite.app, &owner, &set, &[]).unwrap();
+    }
+    suite.contract.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+    suite.contract.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+    suite.contract.create_counter(&mut suite.app, &sender, "named", Coin::new(1, "atom"), None).unwrap();
+
+    let snapshot = suite.contract.query_export_state(&suite.app, None, None).unwrap();
+    assert_eq!(snapshot.next, None);
+    assert_eq!(snapshot.counter, 2);
+    assert_eq!(snapshot.counters, [CounterSnapshot {
+        id: "named".to_owned(),
+        owner: "sender".to_owned(),
+        counter: 0,
+        minimal_donation: Coin::new(1, "atom"),
+    }]);
+    assert_eq!(snapshot.owner, "owner");
+    assert_eq!(snapshot.contract_info.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(snapshot.fee, Some(fee));
+    assert_eq!(snapshot.fees_collected, coins(2, "atom"));
+    assert_eq!(snapshot.tier_thresholds.len(), 1);
+    assert_eq!(snapshot.donors, [DonorSnapshot {
+        counter_id: DEFAULT_COUNTER.to_owned(),
+        address: "sender".to_owned(),
+        donated: coins(20, "atom"),
+        tier: Some(Tier::Silver),
+    }]);
+    assert_eq!(snapshot.attestors.len(), 1);
+    let kinds: Vec<_> = snapshot.audit_log.iter().map(|entry| entry.kind).collect();
+    #[cfg(feature = "permits")]
+    assert_eq!(kinds, [AuditKind::SetTierThresholds, AuditKind::SetAttestor, AuditKind::SetOwnerPubkey]);
+    #[cfg(not(feature = "permits"))]
+    assert_eq!(kinds, [AuditKind::SetTierThresholds, AuditKind::SetAttestor]);
+
+    let msg = InstantiateMsg {
+        counter: 0,
+        minimal_donation: Coin::new(10, "atom"),
+        owner: None,
+        grace_period: None,
+        fee: None,
+        snapshot: Some(snapshot.clone()),
+    };
+    let imported = CountingContract::instantiate_with_msg(
+        &mut suite.app, suite.code_id, &sender, None, "Imported", &msg,
+    )
     .unwrap();
 
-    contract
-        .donate(&mut app, &sender, &coins(10, "atom"))
-        .unwrap();
-    
-    let contract = CountingContract::migrate(&mut app, &admin, contract.addr(), new_code_id).unwrap();
-    
-    let resp = contract.query_value(&app).unwrap();
-    assert_eq!(resp.value, 1);
+    assert_eq!(imported.query_export_state(&suite.app, None, None).unwrap(), snapshot);
+    assert_eq!(imported.query_config(&suite.app).unwrap().owner, "owner");
+    assert_eq!(imported.query_donors_by_tier(&suite.app, Tier::Silver).unwrap(), [sender]);
 
-    let state = STATE.query(&app.wrap(), contract.addr().clone()).unwrap();
-    assert_eq!(state, State {
-        counter: 1,
-        minimal_donation: Coin::new(10, "atom")
-    });    
-    
+    //the imported audit log goes on with the entries of the new contract
+    imported.withdraw(&mut suite.app, &owner).unwrap();
+    let log = imported.query_audit_log(&suite.app, None, None).unwrap().entries;
+    assert_eq!(log.len(), snapshot.audit_log.len() + 1);
+    assert_eq!(audit::verify(&log, None), Ok(()));
+}
+
+#[test]
+#[cfg(feature = "export")]
+fn import_rejects_invalid_snapshot() {
+    let mut suite = SuiteBuilder::new().build();
+    let owner = suite.owner.clone();
+    let set = ExecuteMsg::SetTierThresholds { counter_id: None, denom: "atom".to_owned(), thresholds: None };
+    suite.contract.execute(&mut suite.app, &owner, &set, &[]).unwrap();
+    let snapshot = suite.contract.query_export_state(&suite.app, None, None).unwrap();
+
+    let mut import = |snapshot: StateSnapshot| {
+        let msg = InstantiateMsg {
+            counter: 0,
+            minimal_donation: snapshot.minimal_donation.clone(),
+            owner: None,
+            grace_period: None,
+            fee: None,
+            snapshot: Some(snapshot),
+        };
+        CountingContract::instantiate_with_msg(&mut suite.app, suite.code_id, &owner, None, "Imported", &msg)
+            .unwrap_err()
+    };
+
+    let err = import(StateSnapshot { version: SNAPSHOT_VERSION + 1, ..snapshot.clone() });
+    assert!(matches!(err, ContractError::InvalidSnapshot { .. }), "{err}");
+
+    let mut newer = snapshot.clone();
+    newer.contract_info.version = "99.0.0".to_owned();
+    let err = import(newer);
+    assert!(matches!(err, ContractError::InvalidSnapshot { .. }), "{err}");
+
+    let err = import(StateSnapshot { minimal_donation: Coin::new(10, "a".repeat(129)), ..snapshot.clone() });
+    assert!(matches!(err, ContractError::InvalidSnapshot { .. }), "{err}");
+
+    let err = import(StateSnapshot { owner: String::new(), ..snapshot.clone() });
+    assert!(matches!(err, ContractError::InvalidSnapshot { .. }), "{err}");
+
+    let counter = CounterSnapshot {
+        id: DEFAULT_COUNTER.to_owned(),
+        owner: "owner".to_owned(),
+        counter: 0,
+        minimal_donation: Coin::new(1, "atom"),
+    };
+    let err = import(StateSnapshot { counters: vec![counter.clone()], ..snapshot.clone() });
+    assert!(matches!(err, ContractError::InvalidSnapshot { .. }), "{err}");
+
+    let counter = CounterSnapshot { id: "named".to_owned(), ..counter };
+    let err = import(StateSnapshot { counters: vec![counter.clone(), counter.clone()], ..snapshot.clone() });
+    assert!(matches!(err, ContractError::InvalidSnapshot { .. }), "{err}");
+
+    let counters = (0..=MAX_SNAPSHOT_ENTRIES)
+        .map(|idx| CounterSnapshot { id: format!("counter-{idx}"), ..counter.clone() })
+        .collect();
+    let err = import(StateSnapshot { counters, ..snapshot.clone() });
+    assert_eq!(err.to_string(), format!("Invalid snapshot - more than {MAX_SNAPSHOT_ENTRIES} entries in a page"));
+
+    let cursor = SnapshotCursor { list: SnapshotList::Counters, key: Binary::from(b"named") };
+    let err = import(StateSnapshot { start_after: Some(cursor), ..snapshot.clone() });
+    assert_eq!(err.to_string(), "Invalid snapshot - the instantiate message takes the first page");
+
+    let donor = DonorSnapshot {
+        counter_id: "missing".to_owned(),
+        address: "donor".to_owned(),
+        donated: coins(10, "atom"),
+        tier: None,
+    };
+    let err = import(StateSnapshot { donors: vec![donor.clone()], ..snapshot.clone() });
+    assert_eq!(err.to_string(), "Invalid snapshot - donors entry of unknown counter missing");
+
+    let donor = DonorSnapshot { counter_id: DEFAULT_COUNTER.to_owned(), ..donor };
+    let err = import(StateSnapshot { donors: vec![donor.clone(), donor.clone()], ..snapshot.clone() });
+    assert_eq!(err.to_string(), "Invalid snapshot - donors entry listed twice");
+
+    let thresholds = TierThresholdsSnapshot {
+        counter_id: DEFAULT_COUNTER.to_owned(),
+        denom: "atom".to_owned(),
+        thresholds: TierThresholds { bronze: Uint128::new(10), silver: Uint128::new(5), gold: Uint128::new(20) },
+    };
+    let err = import(StateSnapshot { tier_thresholds: vec![thresholds.clone()], ..snapshot.clone() });
+    assert!(matches!(err, ContractError::InvalidSnapshot { .. }), "{err}");
+
+    //the limit is on the page, however its entries are spread over the lists
+    let tier_thresholds = (0..MAX_SNAPSHOT_ENTRIES / 2)
+        .map(|idx| TierThresholdsSnapshot { denom: format!("denom{idx}"), ..thresholds.clone() })
+        .collect();
+    let donors = (0..=MAX_SNAPSHOT_ENTRIES / 2)
+        .map(|idx| DonorSnapshot { address: format!("donor{idx}"), ..donor.clone() })
+        .collect();
+    let err = import(StateSnapshot { tier_thresholds, donors, ..snapshot.clone() });
+    assert_eq!(err.to_string(), format!("Invalid snapshot - more than {MAX_SNAPSHOT_ENTRIES} entries in a page"));
+
+    //a log which doesn't verify can't have come from an export
+    let mut audit_log = snapshot.audit_log.clone();
+    audit_log[0].after = "{}".to_owned();
+    let err = import(StateSnapshot { audit_log, ..snapshot });
+    assert_eq!(err.to_string(), "Invalid snapshot - audit log entry 0 doesn't match the hash chain");
+}
+
+#[test]
+#[cfg(feature = "export")]
+fn export_and_import_in_pages() {
+    let sender = Addr::unchecked("sender");
+    let mut suite = SuiteBuilder::new()
+        .with_funds("sender", &coins(50, "atom"))
+        .build();
+    let owner = suite.owner.clone();
+    for id in ["first", "second", "third"] {
+        suite.contract.create_counter(&mut suite.app, &sender, id, Coin::new(1, "atom"), None).unwrap();
+    }
+    for donor in ["alice", "bob", "carol"] {
+        let msg = ExecuteMsg::DonateFor { counter_id: None, beneficiary: donor.to_owned() };
+        suite.contract.execute(&mut suite.app, &sender, &msg, &coins(10, "atom")).unwrap();
+    }
+    //empty donations leave no donor behind
+    suite.contract.donate(&mut suite.app, &sender, &[]).unwrap();
+    let set = ExecuteMsg::SetTierThresholds {
+        counter_id: None,
+        denom: "atom".to_owned(),
+        thresholds: Some(TierThresholds { bronze: Uint128::new(5), silver: Uint128::new(15), gold: Uint128::new(30) }),
+    };
+    suite.contract.execute(&mut suite.app, &owner, &set, &[]).unwrap();
+
+    let full = suite.contract.query_export_state(&suite.app, None, None).unwrap();
+    assert_eq!(full.counters.len(), 3);
+    let donors: Vec<_> = full.donors.iter().map(|donor| donor.address.as_str()).collect();
+    assert_eq!(donors, ["alice", "bob", "carol"]);
+    assert_eq!(full.audit_log.len(), 1);
+
+    //3 counters, 1 tier thresholds, 3 donors and 1 audit entry
+    let mut pages = vec![suite.contract.query_export_state(&suite.app, None, Some(3)).unwrap()];
+    while let Some(next) = pages.last().unwrap().next.clone() {
+        pages.push(suite.contract.query_export_state(&suite.app, Some(next), Some(3)).unwrap());
+    }
+    assert_eq!(pages.len(), 3);
+    let donors: Vec<_> = pages.iter().map(|page| page.donors.len()).collect();
+    assert_eq!(donors, [0, 2, 1]);
+    assert_eq!(pages[2].audit_log, full.audit_log);
+
+    let msg = InstantiateMsg { snapshot: Some(pages[0].clone()), ..InstantiateMsg::new(Coin::new(10, "atom")) };
+    let imported =
+        CountingContract::instantiate_with_msg(&mut suite.app, suite.code_id, &sender, None, "Imported", &msg).unwrap();
+
+    //nothing but the next page from the importer until the import completes
+    let err = imported.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap_err();
+    assert_eq!(err, ContractError::ImportInProgress {});
+    let err = imported.import_state(&mut suite.app, &owner, pages[1].clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: sender.to_string() });
+    let err = imported.import_state(&mut suite.app, &sender, pages[2].clone()).unwrap_err();
+    assert_eq!(err.to_string(), "Invalid snapshot - the page doesn't follow the one imported last");
+    imported.import_state(&mut suite.app, &sender, pages[1].clone()).unwrap();
+    let err = imported.import_state(&mut suite.app, &sender, pages[1].clone()).unwrap_err();
+    assert_eq!(err.to_string(), "Invalid snapshot - the page doesn't follow the one imported last");
+    imported.import_state(&mut suite.app, &sender, pages[2].clone()).unwrap();
+
+    let err = imported.import_state(&mut suite.app, &sender, pages[2].clone()).unwrap_err();
+    assert_eq!(err, ContractError::NoImportInProgress {});
+    assert_eq!(imported.query_export_state(&suite.app, None, None).unwrap(), full);
+    imported.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+}
+
+#[test]
+fn schema_follows_features() {
+    let query = serde_json::to_string(&cosmwasm_schema::schema_for!(QueryMsg)).unwrap();
+    let execute = serde_json::to_string(&cosmwasm_schema::schema_for!(ExecuteMsg)).unwrap();
+    assert_eq!(query.contains("export_state"), cfg!(feature = "export"));
+    assert_eq!(query.contains(r#""matching""#), cfg!(feature = "matching"));
+    assert_eq!(execute.contains("fund_matching"), cfg!(feature = "matching"));
+    assert_eq!(query.contains("owner_pubkey"), cfg!(feature = "permits"));
+    assert_eq!(execute.contains("withdraw_with_permit"), cfg!(feature = "permits"));
+    assert_eq!(query.contains(r#""round""#), cfg!(feature = "rounds"));
+    assert_eq!(execute.contains("create_round"), cfg!(feature = "rounds"));
+}
+
+#[test]
+fn decode_storage_dumps() {
+    let sender = Addr::unchecked("sender");
+    let mut suite = SuiteBuilder::new()
+        .with_admin("admin")
+        .with_funds("sender", &coins(10, "atom"))
+        .with_legacy_v0_1()
+        .build();
+    suite.contract.donate(&mut suite.app, &sender, &coins(10, "atom")).unwrap();
+
+    let dump = decoder::decode(&suite.app.dump_wasm_raw(suite.contract.addr()));
+    assert_eq!(dump.layout, Layout::V0_1);
+    assert_eq!(dump.flagged().count(), 0);
+    assert!(dump.entries.iter().any(|entry| entry.value == Some(KnownValue::Counter(1))));
+
+    suite.migrate().unwrap();
+    let mut records = suite.app.dump_wasm_raw(suite.contract.addr());
+    let dump = decoder::decode(&records);
+    assert_eq!(dump.layout, Layout::Counters);
+    assert_eq!(dump.flagged().count(), 0);
+    assert!(dump.entries.iter().any(|entry| matches!(
+        &entry.value,
+        Some(KnownValue::NamedCounter { id, state }) if id == DEFAULT_COUNTER && state.counter == 1
+    )));
+    assert!(dump.entries.iter().any(|entry| matches!(entry.value, Some(KnownValue::ContractInfo(_)))));
+
+    //leftovers of migrations done before 0.1 items were removed
+    records.push((b"counter".to_vec(), b"1".to_vec()));
+    records.push((b"unexpected".to_vec(), b"{}".to_vec()));
+    let dump = decoder::decode(&records);
+    let flagged: Vec<_> = dump.flagged().map(|entry| (entry.key.as_str(), entry.status)).collect();
+    assert_eq!(flagged, [("counter", KeyStatus::Legacy), ("unexpected", KeyStatus::Unknown)]);
+}
+
+#[test]
+fn parse_state_query_dump() {
+    let dump = br#"{"models":[{"key":"636F756E746572","value":"Mg=="}],"pagination":{}}"#;
+    let records = decoder::parse_dump(dump).unwrap();
+    assert_eq!(records, [(b"counter".to_vec(), b"2".to_vec())]);
+    assert_eq!(decoder::decode(&records).entries[0].value, Some(KnownValue::Counter(2)));
 }
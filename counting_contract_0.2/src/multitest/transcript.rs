@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Empty, Event};
+use cw_multi_test::{App, Executor};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::{into_contract_error, CountingContract};
+use crate::error::ContractError;
+use crate::msg::ExecuteMsg;
+
+//set to regenerate golden files instead of comparing against them
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ContractState {
+    pub value: u64,
+    pub balance: Vec<Coin>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ok { events: Vec<Event> },
+    Err { error: String },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Entry {
+    pub sender: Addr,
+    pub msg: Value,
+    pub funds: Vec<Coin>,
+    pub outcome: Outcome,
+    pub state: ContractState,
+}
+
+//wraps a contract handle and records every call made through it
+pub struct RecordingContract {
+    contract: CountingContract,
+    entries: Vec<Entry>,
+}
+
+impl RecordingContract {
+    pub fn new(contract: CountingContract) -> Self {
+        Self {
+            contract,
+            entries: vec![],
+        }
+    }
+
+    pub fn contract(&self) -> &CountingContract {
+        &self.contract
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn donate(&mut self, app: &mut App, sender: &Addr, funds: &[Coin]) -> Result<(), ContractError> {
+        let msg = self.contract.as_addr().donate_msg(funds.to_vec())?;
+        self.record(app, sender, json!({ "execute": ExecuteMsg::Donate { counter_id: None } }), funds, msg)
+    }
+
+    pub fn withdraw(&mut self, app: &mut App, sender: &Addr) -> Result<(), ContractError> {
+        let msg = self.contract.as_addr().withdraw_msg()?;
+        self.record(app, sender, json!({ "execute": ExecuteMsg::Withdraw { counter_id: None } }), &[], msg)
+    }
+
+    pub fn migrate(&mut self, app: &mut App, sender: &Addr, code_id: u64) -> Result<(), ContractError> {
+        let msg = self.contract.as_addr().migrate_msg(code_id, &Empty {})?;
+        let recorded = json!({ "migrate": { "code_id": code_id, "msg": Empty {} } });
+        self.record(app, sender, recorded, &[], msg)
+    }
+
+    fn record(
+        &mut self,
+        app: &mut App,
+        sender: &Addr,
+        recorded: Value,
+        funds: &[Coin],
+        msg: CosmosMsg,
+    ) -> Result<(), ContractError> {
+        let result = app.execute(sender.clone(), msg).map_err(into_contract_error);
+        let outcome = match &result {
+            Ok(resp) => Outcome::Ok {
+                events: resp.events.clone(),
+            },
+            Err(err) => Outcome::Err {
+                error: err.to_string(),
+            },
+        };
+
+        let state = ContractState {
+            value: self.contract.query_value(app)?.value,
+            balance: app.wrap().query_all_balances(self.contract.addr())?,
+        };
+        self.entries.push(Entry {
+            sender: sender.clone(),
+            msg: recorded,
+            funds: funds.to_vec(),
+            outcome,
+            state,
+        });
+
+        result.map(|_| ())
+    }
+
+    pub fn transcript(&self) -> String {
+        serde_json::to_string_pretty(&self.entries).unwrap() + "\n"
+    }
+
+    //compares the transcript with golden/<name>.json, or rewrites it when UPDATE_GOLDEN is set
+    #[track_caller]
+    pub fn assert_golden(&self, name: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("golden")
+            .join(format!("{name}.json"));
+        let actual = self.transcript();
+
+        if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|err| {
+            panic!(
+                "cannot read {}: {err}, run with {UPDATE_GOLDEN_ENV}=1 to create it",
+                path.display()
+            )
+        });
+        if expected != actual {
+            panic!(
+                "transcript differs from {}, run with {UPDATE_GOLDEN_ENV}=1 to accept it\n{}",
+                path.display(),
+                line_diff(&expected, &actual)
+            );
+        }
+    }
+}
+
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+    let mut diff = String::new();
+    for idx in 0..expected.len().max(actual.len()) {
+        match (expected.get(idx), actual.get(idx)) {
+            (Some(exp), Some(act)) if exp == act => (),
+            (exp, act) => {
+                if let Some(exp) = exp {
+                    diff += &format!("{:>4} - {exp}\n", idx + 1);
+                }
+                if let Some(act) = act {
+                    diff += &format!("{:>4} + {act}\n", idx + 1);
+                }
+            }
+        }
+    }
+    diff
+}
@@ -0,0 +1,241 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use cosmwasm_std::{coins, Addr, Coin, CosmosMsg, Empty, Event, Response};
+use counting_contract_0_1_0::msg::InstantiateMsg as InstantiateMsg_0_1_0;
+use cw_multi_test::{AppResponse, Executor};
+
+use super::scenario::{Backend, MultiTest, Scenario, Version};
+use super::SuiteBuilder;
+use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, ValueResp};
+use crate::vm::{load_wasm, Call, VmApp};
+
+//runs the wasm build in cosmwasm-vm and compares it with the native build in multitest,
+//covering what ContractWrapper skips: serialization, entry point exports and the library feature
+
+fn artifacts() -> &'static (Vec<u8>, Vec<u8>) {
+    static ARTIFACTS: OnceLock<(Vec<u8>, Vec<u8>)> = OnceLock::new();
+    ARTIFACTS.get_or_init(|| {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let wasm = load_wasm(manifest_dir, "counting_contract").unwrap();
+        let legacy_wasm = load_wasm(
+            &manifest_dir.join("../counting_contract"),
+            "counting_contract_0_1",
+        )
+        .unwrap();
+        (wasm, legacy_wasm)
+    })
+}
+
+struct Vm {
+    app: VmApp,
+    code_id: u64,
+    legacy_code_id: u64,
+    contract: Option<Addr>,
+}
+
+impl Vm {
+    fn new() -> Self {
+        let (wasm, legacy_wasm) = artifacts();
+        let mut app = VmApp::new();
+        let code_id = app.store_code(wasm.clone()).unwrap();
+        let legacy_code_id = app.store_code(legacy_wasm.clone()).unwrap();
+        Self {
+            app,
+            code_id,
+            legacy_code_id,
+            contract: None,
+        }
+    }
+
+    fn execute(&mut self, sender: &Addr, msg: &ExecuteMsg, funds: &[Coin]) -> Call {
+        let contract = self.contract.clone().expect("contract instantiated");
+        self.app.execute(&contract, sender, msg, funds)
+    }
+}
+
+impl Backend for Vm {
+    fn init_balance(&mut self, addr: &Addr, coins: Vec<Coin>) {
+        self.app.init_balance(addr, coins);
+    }
+
+    fn instantiate(
+        &mut self,
+        version: Version,
+        sender: &Addr,
+        admin: Option<&Addr>,
+        _label: &str,
+        minimal_donation: Coin,
+    ) -> Result<(), String> {
+        let (contract, call) = match version {
+            Version::V0_1 => {
+                let msg = InstantiateMsg_0_1_0 {
+                    counter: 0,
+                    minimal_donation,
+                };
+                self.app
+                    .instantiate(self.legacy_code_id, sender, &msg, &[], admin)
+            }
+            Version::V0_2 => {
+                let msg = InstantiateMsg::new(minimal_donation);
+                self.app.instantiate(self.code_id, sender, &msg, &[], admin)
+            }
+        };
+        call.result?;
+        self.contract = Some(contract);
+        Ok(())
+    }
+
+    fn contract(&self) -> Option<Addr> {
+        self.contract.clone()
+    }
+
+    fn donate(&mut self, sender: &Addr, funds: &[Coin]) -> Result<(), String> {
+        self.execute(sender, &ExecuteMsg::Donate { counter_id: None }, funds)
+            .result
+            .map(|_| ())
+    }
+
+    fn withdraw(&mut self, sender: &Addr) -> Result<(), String> {
+        self.execute(sender, &ExecuteMsg::Withdraw { counter_id: None }, &[])
+            .result
+            .map(|_| ())
+    }
+
+    fn migrate(&mut self, sender: &Addr, _version: Version) -> Result<(), String> {
+        let contract = self.contract.clone().expect("contract instantiated");
+        self.app
+            .migrate(&contract, sender, self.code_id, &Empty {})
+            .result
+            .map(|_| ())
+    }
+
+    fn advance_block(&mut self, blocks: u64, seconds: u64) {
+        self.app.advance_block(blocks, seconds);
+    }
+
+    fn query_value(&mut self) -> Result<u64, String> {
+        let contract = self.contract.clone().expect("contract instantiated");
+        self.app
+            .query_json::<ValueResp>(&contract, &QueryMsg::Value { counter_id: None })
+            .map(|resp| resp.value)
+    }
+
+    fn query_balance(&mut self, addr: &Addr) -> Result<Vec<Coin>, String> {
+        Ok(self.app.balance(addr))
+    }
+}
+
+//events of a vm response the way multitest reports them
+fn vm_events(contract: &Addr, resp: &Response) -> Vec<Event> {
+    let wasm = (!resp.attributes.is_empty()).then(|| ("wasm".to_owned(), &resp.attributes));
+    wasm.into_iter()
+        .chain(
+            resp.events
+                .iter()
+                .map(|event| (format!("wasm-{}", event.ty), &event.attributes)),
+        )
+        .map(|(ty, attributes)| {
+            Event::new(ty)
+                .add_attribute("_contract_addr", contract)
+                .add_attributes(attributes.clone())
+        })
+        .collect()
+}
+
+fn multitest_events(resp: &AppResponse) -> Vec<Event> {
+    resp.events
+        .iter()
+        .filter(|event| event.ty == "wasm" || event.ty.starts_with("wasm-"))
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn scenarios_match_multitest() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+    for file in ["migration.toml", "withdraw.json"] {
+        let scenario = Scenario::load(&dir.join(file)).unwrap();
+        let expected = scenario.run_on(&mut MultiTest::new());
+        let actual = scenario.run_on(&mut Vm::new());
+
+        assert!(actual.passed(), "{}", actual);
+        for (expected, actual) in expected.steps.iter().zip(&actual.steps) {
+            assert_eq!(
+                expected.observed, actual.observed,
+                "{file} step #{} {}",
+                expected.index, expected.action
+            );
+        }
+    }
+}
+
+#[test]
+fn responses_match_multitest() {
+    let alice = Addr::unchecked("alice");
+    let funds = [Coin::new(20, "atom"), Coin::new(10, "btc")];
+    let mut suite = SuiteBuilder::new().with_funds("alice", &funds).build();
+    let owner = suite.owner.clone();
+
+    let mut vm = Vm::new();
+    vm.init_balance(&alice, funds.to_vec());
+    vm.instantiate(Version::V0_2, &owner, None, "", Coin::new(10, "atom"))
+        .unwrap();
+
+    let donate = |counter_id: Option<&str>| ExecuteMsg::Donate {
+        counter_id: counter_id.map(str::to_owned),
+    };
+    let withdraw = |counter_id: Option<&str>| ExecuteMsg::Withdraw {
+        counter_id: counter_id.map(str::to_owned),
+    };
+    let create_counter = ExecuteMsg::CreateCounter {
+        id: "named".to_owned(),
+        minimal_donation: Coin::new(5, "btc"),
+        owner: None,
+    };
+    let calls = [
+        (&alice, donate(None), coins(10, "atom")),
+        (&alice, donate(None), vec![Coin::new(5, "atom"), Coin::new(5, "btc")]),
+        (&alice, donate(Some("named")), coins(5, "atom")),
+        (&alice, create_counter.clone(), vec![]),
+        (&alice, create_counter, vec![]),
+        (&alice, donate(Some("named")), coins(5, "btc")),
+        (&alice, withdraw(None), vec![]),
+        (&owner, withdraw(None), vec![]),
+        (&owner, withdraw(None), vec![]),
+        (&owner, withdraw(Some("named")), vec![]),
+        (&alice, withdraw(Some("named")), vec![]),
+    ];
+    for (sender, msg, funds) in calls {
+        let expected = suite
+            .contract
+            .as_addr()
+            .execute_msg(&msg, funds.clone())
+            .map_err(anyhow::Error::from)
+            .and_then(|msg: CosmosMsg| suite.app.execute(sender.clone(), msg));
+        let actual = vm.execute(sender, &msg, &funds).result;
+
+        match (expected, actual) {
+            (Ok(expected), Ok(actual)) => {
+                assert_eq!(
+                    multitest_events(&expected),
+                    vm_events(suite.contract.addr(), &actual),
+                    "{msg:?}"
+                );
+                assert_eq!(expected.data, actual.data, "{msg:?}");
+            }
+            (Err(expected), Err(actual)) => {
+                assert_eq!(expected.root_cause().to_string(), actual, "{msg:?}")
+            }
+            (expected, actual) => panic!("{msg:?}: multitest {expected:?}, wasm {actual:?}"),
+        }
+
+        for addr in [&owner, &alice, suite.contract.addr()] {
+            assert_eq!(
+                suite.app.wrap().query_all_balances(addr).unwrap(),
+                vm.app.balance(addr),
+                "balance of {addr} after {msg:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,116 @@
+pub mod execute {
+    use cosmwasm_std::{BankMsg, Binary, Coin, DepsMut, Empty, Env, MessageInfo, Response};
+    use counting_contract_interface::permit;
+
+    use crate::contract::execute::{counter_id, load_counter, settle_donations};
+    use crate::error::ContractError;
+    use crate::msg::{AuditKind, FeeMode, OwnerPubkey, WithdrawPermit};
+    use crate::signatures::{valid_key, verify, Key};
+    use crate::state::{add_coins, COUNTERS, OWNER_PUBKEYS, PERMIT_NONCES};
+    use crate::{audit, fees};
+
+    //takes amount out of balance, failing on the first coin it doesn't cover
+    fn take(balance: &mut Vec<Coin>, amount: &[Coin]) -> Result<(), ContractError> {
+        for coin in amount {
+            let held = balance
+                .iter_mut()
+                .find(|held| held.denom == coin.denom && held.amount >= coin.amount)
+                .ok_or_else(|| ContractError::InsufficientBalance { amount: coin.clone() })?;
+            held.amount -= coin.amount;
+        }
+        balance.retain(|coin| !coin.amount.is_zero());
+        Ok(())
+    }
+
+    pub fn set_owner_pubkey(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        counter_id: Option<String>,
+        pubkey: Option<OwnerPubkey>,
+    ) -> Result<Response, ContractError> {
+        let id = self::counter_id(counter_id);
+        let counter = load_counter(deps.storage, &id)?;
+        if info.sender != counter.owner {
+            return Err(ContractError::Unauthorized { owner: counter.owner.to_string() });
+        }
+
+        let previous = OWNER_PUBKEYS.may_load(deps.storage, &id)?;
+        match &pubkey {
+            Some(pubkey) if !valid_key(pubkey.into()) => return Err(ContractError::InvalidOwnerPubkey {}),
+            Some(pubkey) => OWNER_PUBKEYS.save(deps.storage, &id, pubkey)?,
+            None => OWNER_PUBKEYS.remove(deps.storage, &id),
+        }
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::SetOwnerPubkey, Some(id.clone()), &previous, &pubkey)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "set_owner_pubkey")
+            .add_attribute("counter_id", id);
+        Ok(resp)
+    }
+
+    pub fn withdraw_with_permit(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        permit: WithdrawPermit,
+        signature: Binary,
+    ) -> Result<Response, ContractError> {
+        let id = self::counter_id(permit.counter_id.clone());
+        let mut counter = load_counter(deps.storage, &id)?;
+        let pubkey = OWNER_PUBKEYS
+            .may_load(deps.storage, &id)?
+            .ok_or_else(|| ContractError::OwnerPubkeyNotSet { id: id.clone() })?;
+
+        let hash = permit::digest(env.contract.address.as_str(), &permit)?;
+        if !verify(deps.api, &hash, &signature, Key::from(&pubkey)) {
+            return Err(ContractError::InvalidPermit {});
+        }
+        if env.block.time >= permit.expires {
+            return Err(ContractError::PermitExpired { nonce: permit.nonce });
+        }
+        if PERMIT_NONCES.has(deps.storage, (&id, permit.nonce)) {
+            return Err(ContractError::PermitUsed { nonce: permit.nonce });
+        }
+        let receiver = deps.api.addr_validate(&permit.receiver)?;
+        PERMIT_NONCES.save(deps.storage, (&id, permit.nonce), &Empty {})?;
+
+        let donation_fee = settle_donations(deps.storage, &env, &id, &mut counter)?;
+        let before = counter.balance.clone();
+        let mut amount = vec![];
+        add_coins(&mut amount, &permit.amount);
+        take(&mut counter.balance, &amount)?;
+        COUNTERS.save(deps.storage, &id, &counter)?;
+        //counter balance before and after, the owner signed it
+        audit::record(deps.storage, &env, Some(&counter.owner), AuditKind::WithdrawWithPermit, Some(id.clone()), &before, &counter.balance)?;
+        let (amount, withdraw_fee) = fees::charge(deps.storage, FeeMode::Withdraw, amount)?;
+
+        let mut resp = Response::new().add_messages(donation_fee).add_messages(withdraw_fee);
+        if !amount.is_empty() {
+            resp = resp.add_message(BankMsg::Send { to_address: receiver.to_string(), amount });
+        }
+
+        let resp = resp
+            .add_attribute("action", "withdraw_with_permit")
+            .add_attribute("counter_id", id)
+            .add_attribute("sender", info.sender.to_string())
+            .add_attribute("receiver", receiver)
+            .add_attribute("nonce", permit.nonce.to_string());
+        Ok(resp)
+    }
+}
+
+pub mod query {
+    use cosmwasm_std::{Deps, StdResult};
+
+    use crate::contract::execute::counter_id;
+    use crate::msg::OwnerPubkeyResp;
+    use crate::state::{COUNTERS, OWNER_PUBKEYS};
+
+    pub fn owner_pubkey(deps: Deps, counter_id: Option<String>) -> StdResult<OwnerPubkeyResp> {
+        let id = self::counter_id(counter_id);
+        COUNTERS.load(deps.storage, &id)?;
+        let pubkey = OWNER_PUBKEYS.may_load(deps.storage, &id)?;
+        Ok(OwnerPubkeyResp { pubkey })
+    }
+}
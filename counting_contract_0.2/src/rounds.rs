@@ -0,0 +1,338 @@
+use cosmwasm_std::{Decimal256, Order, StdResult, Storage, Uint128, Uint256};
+
+use crate::state::{Project, PROJECTS};
+
+//keeps finalizing a round within the gas limit
+const MAX_PROJECTS: u32 = 50;
+
+//every Uint128 fits, even with 18 decimal places
+fn decimal(amount: Uint128) -> Decimal256 {
+    Decimal256::from_ratio(amount, 1u128)
+}
+
+//quadratic funding makes the ideal funding of a project the square of the summed square roots
+//of its contributions, the matching pool pays the part above what donors contributed. the
+//square stays far below the Decimal256 limit for any Uint128 contributions, saturating only
+//keeps finalizing a round from failing
+fn subsidy(project: &Project) -> Decimal256 {
+    let ideal = project.sum_sqrt.saturating_pow(2);
+    ideal.saturating_sub(decimal(project.contributed))
+}
+
+//a donor's contributions count as one, only the square root of their total is summed
+fn add_contribution(project: &mut Project, previous: Uint128, amount: Uint128) -> StdResult<()> {
+    let total = previous.checked_add(amount)?;
+    project.sum_sqrt = project
+        .sum_sqrt
+        .checked_sub(decimal(previous).sqrt())?
+        .checked_add(decimal(total).sqrt())?;
+    project.contributed += amount;
+    if previous.is_zero() {
+        project.donors += 1;
+    }
+    Ok(())
+}
+
+//splits the pool in proportion to the subsidies, rounding down
+fn matching_shares(storage: &dyn Storage, round_id: &str, pool: Uint128) -> StdResult<Vec<(String, Project, Uint128)>> {
+    let projects = PROJECTS
+        .prefix(round_id)
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (id, project) = item?;
+            let subsidy = subsidy(&project);
+            Ok((id, project, subsidy))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let total = projects
+        .iter()
+        .fold(Decimal256::zero(), |total, (_, _, subsidy)| total.saturating_add(*subsidy));
+    //capped by what is left of the pool, so finalizing never hands out more than it holds
+    let mut remaining = pool;
+    let shares = projects
+        .into_iter()
+        .map(|(id, project, subsidy)| {
+            let share = if total.is_zero() {
+                Uint128::zero()
+            } else {
+                let share = Uint256::from(pool).multiply_ratio(subsidy.atomics(), total.atomics());
+                Uint128::try_from(share).unwrap_or(remaining).min(remaining)
+            };
+            remaining -= share;
+            (id, project, share)
+        })
+        .collect();
+    Ok(shares)
+}
+
+pub mod execute {
+    use cosmwasm_std::{Addr, BankMsg, Coin, Decimal256, DepsMut, Env, MessageInfo, Response, Storage, Timestamp, Uint128};
+
+    use super::{matching_shares, MAX_PROJECTS};
+    use crate::audit;
+    use crate::contract::execute::load_counter;
+    use crate::contract::{validate_id, MAX_DENOM_LEN};
+    use crate::error::ContractError;
+    use crate::msg::{AuditKind, DEFAULT_COUNTER};
+    use crate::state::{Project, Round, CONTRIBUTIONS, PROJECTS, ROUNDS};
+
+    fn load_round(storage: &dyn Storage, id: &str) -> Result<Round, ContractError> {
+        ROUNDS
+            .may_load(storage, id)?
+            .ok_or_else(|| ContractError::RoundNotFound { id: id.to_owned() })
+    }
+
+    fn load_project(storage: &dyn Storage, round_id: &str, project_id: &str) -> Result<Project, ContractError> {
+        PROJECTS
+            .may_load(storage, (round_id, project_id))?
+            .ok_or_else(|| ContractError::ProjectNotFound {
+                round_id: round_id.to_owned(),
+                project_id: project_id.to_owned(),
+            })
+    }
+
+    fn ensure_open(env: &Env, id: &str, round: &Round) -> Result<(), ContractError> {
+        if env.block.time >= round.end_time {
+            return Err(ContractError::RoundEnded { id: id.to_owned() });
+        }
+        Ok(())
+    }
+
+    pub fn create_round(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        id: String,
+        denom: String,
+        end_time: Timestamp,
+    ) -> Result<Response, ContractError> {
+        let owner = load_counter(deps.storage, DEFAULT_COUNTER)?.owner;
+        if info.sender != owner {
+            return Err(ContractError::Unauthorized { owner: owner.to_string() });
+        }
+        validate_id(&id)?;
+        if denom.is_empty() || denom.len() > MAX_DENOM_LEN {
+            return Err(ContractError::InvalidDenom { denom });
+        }
+        if ROUNDS.has(deps.storage, &id) {
+            return Err(ContractError::RoundExists { id });
+        }
+        if end_time <= env.block.time {
+            return Err(ContractError::InvalidRoundEnd {});
+        }
+
+        let round = Round {
+            admin: info.sender.clone(),
+            denom,
+            end_time,
+            pool: Uint128::zero(),
+            projects: 0,
+            finalized: false,
+        };
+        ROUNDS.save(deps.storage, &id, &round)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "create_round")
+            .add_attribute("round_id", id)
+            .add_attribute("admin", info.sender);
+        Ok(resp)
+    }
+
+    pub fn register_project(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        round_id: String,
+        project_id: String,
+        owner: String,
+    ) -> Result<Response, ContractError> {
+        let mut round = load_round(deps.storage, &round_id)?;
+        if info.sender != round.admin {
+            return Err(ContractError::Unauthorized { owner: round.admin.to_string() });
+        }
+        ensure_open(&env, &round_id, &round)?;
+        validate_id(&project_id)?;
+        if PROJECTS.has(deps.storage, (&round_id, &project_id)) {
+            return Err(ContractError::ProjectExists { round_id, project_id });
+        }
+        if round.projects >= MAX_PROJECTS {
+            return Err(ContractError::TooManyProjects { max: MAX_PROJECTS });
+        }
+
+        let project = Project {
+            owner: deps.api.addr_validate(&owner)?,
+            contributed: Uint128::zero(),
+            donors: 0,
+            sum_sqrt: Decimal256::zero(),
+            matching: Uint128::zero(),
+            withdrawn: false,
+        };
+        PROJECTS.save(deps.storage, (&round_id, &project_id), &project)?;
+        round.projects += 1;
+        ROUNDS.save(deps.storage, &round_id, &round)?;
+        //owner of the new project
+        let target = format!("{round_id}/{project_id}");
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::RegisterProject, Some(target), &None::<Addr>, &project.owner)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "register_project")
+            .add_attribute("round_id", round_id)
+            .add_attribute("project_id", project_id)
+            .add_attribute("owner", project.owner);
+        Ok(resp)
+    }
+
+    pub fn fund_round(deps: DepsMut, env: Env, info: MessageInfo, round_id: String) -> Result<Response, ContractError> {
+        let mut round = load_round(deps.storage, &round_id)?;
+        ensure_open(&env, &round_id, &round)?;
+        let amount = cw_utils::must_pay(&info, &round.denom)?;
+        round.pool += amount;
+        ROUNDS.save(deps.storage, &round_id, &round)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "fund_round")
+            .add_attribute("round_id", round_id)
+            .add_attribute("sender", info.sender)
+            .add_attribute("amount", amount);
+        Ok(resp)
+    }
+
+    pub fn donate_to_project(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        round_id: String,
+        project_id: String,
+    ) -> Result<Response, ContractError> {
+        let round = load_round(deps.storage, &round_id)?;
+        ensure_open(&env, &round_id, &round)?;
+        let mut project = load_project(deps.storage, &round_id, &project_id)?;
+        let amount = cw_utils::must_pay(&info, &round.denom)?;
+
+        let key = (round_id.as_str(), project_id.as_str(), &info.sender);
+        let previous = CONTRIBUTIONS.may_load(deps.storage, key)?.unwrap_or_default();
+        let total = previous + amount;
+        CONTRIBUTIONS.save(deps.storage, key, &total)?;
+
+        super::add_contribution(&mut project, previous, amount)?;
+        PROJECTS.save(deps.storage, (&round_id, &project_id), &project)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "donate_to_project")
+            .add_attribute("round_id", round_id)
+            .add_attribute("project_id", project_id)
+            .add_attribute("sender", info.sender)
+            .add_attribute("amount", amount);
+        Ok(resp)
+    }
+
+    pub fn finalize_round(deps: DepsMut, env: Env, info: MessageInfo, round_id: String) -> Result<Response, ContractError> {
+        let mut round = load_round(deps.storage, &round_id)?;
+        if round.finalized {
+            return Err(ContractError::RoundFinalized { id: round_id });
+        }
+        if env.block.time < round.end_time {
+            return Err(ContractError::RoundNotEnded { id: round_id });
+        }
+
+        let mut distributed = Uint128::zero();
+        let mut shares = vec![];
+        for (project_id, mut project, share) in matching_shares(deps.storage, &round_id, round.pool)? {
+            project.matching = share;
+            PROJECTS.save(deps.storage, (&round_id, &project_id), &project)?;
+            distributed += share;
+            shares.push((project_id, share));
+        }
+        round.finalized = true;
+        ROUNDS.save(deps.storage, &round_id, &round)?;
+        //the pool and how it was split among the projects
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::FinalizeRound, Some(round_id.clone()), &round.pool, &shares)?;
+
+        //rounding leftovers, or the whole pool when nobody donated, go back to the round admin
+        let mut resp = Response::new();
+        let leftover = round.pool - distributed;
+        if !leftover.is_zero() {
+            resp = resp.add_message(BankMsg::Send {
+                to_address: round.admin.to_string(),
+                amount: vec![Coin::new(leftover.u128(), round.denom)],
+            });
+        }
+
+        let resp = resp
+            .add_attribute("action", "finalize_round")
+            .add_attribute("round_id", round_id)
+            .add_attribute("distributed", distributed);
+        Ok(resp)
+    }
+
+    pub fn withdraw_project(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        round_id: String,
+        project_id: String,
+    ) -> Result<Response, ContractError> {
+        let round = load_round(deps.storage, &round_id)?;
+        let mut project = load_project(deps.storage, &round_id, &project_id)?;
+        if info.sender != project.owner {
+            return Err(ContractError::Unauthorized { owner: project.owner.to_string() });
+        }
+        if !round.finalized {
+            return Err(ContractError::RoundNotFinalized { id: round_id });
+        }
+
+        let amount = project.contributed + project.matching;
+        if project.withdrawn || amount.is_zero() {
+            return Err(ContractError::NothingToWithdraw { round_id, project_id });
+        }
+        project.withdrawn = true;
+        PROJECTS.save(deps.storage, (&round_id, &project_id), &project)?;
+        //what the project could still withdraw before and after
+        let target = format!("{round_id}/{project_id}");
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::WithdrawProject, Some(target), &amount, &Uint128::zero())?;
+
+        let resp = Response::new()
+            .add_message(BankMsg::Send {
+                to_address: info.sender.to_string(),
+                amount: vec![Coin::new(amount.u128(), &round.denom)],
+            })
+            .add_attribute("action", "withdraw_project")
+            .add_attribute("round_id", round_id)
+            .add_attribute("project_id", project_id)
+            .add_attribute("sender", info.sender);
+        Ok(resp)
+    }
+}
+
+pub mod query {
+    use cosmwasm_std::{Coin, Deps, StdResult};
+
+    use super::matching_shares;
+    use crate::msg::{ProjectResp, RoundResp};
+    use crate::state::ROUNDS;
+
+    pub fn round(deps: Deps, round_id: String) -> StdResult<RoundResp> {
+        let round = ROUNDS.load(deps.storage, &round_id)?;
+        let projects = matching_shares(deps.storage, &round_id, round.pool)?
+            .into_iter()
+            .map(|(id, project, estimate)| ProjectResp {
+                id,
+                owner: project.owner,
+                contributed: project.contributed,
+                donors: project.donors,
+                matching: if round.finalized { project.matching } else { estimate },
+                withdrawn: project.withdrawn,
+            })
+            .collect();
+
+        Ok(RoundResp {
+            id: round_id,
+            admin: round.admin,
+            end_time: round.end_time,
+            pool: Coin::new(round.pool.u128(), round.denom),
+            finalized: round.finalized,
+            projects,
+        })
+    }
+}
@@ -0,0 +1,43 @@
+use cosmwasm_std::Api;
+
+#[cfg(feature = "permits")]
+use crate::msg::OwnerPubkey;
+
+//public key of an attestor or counter owner
+#[derive(Clone, Copy)]
+pub enum Key<'a> {
+    //33 or 65 bytes, compressed or not
+    Secp256k1(&'a [u8]),
+    //only owner keys, attestors sign with secp256k1
+    #[cfg(feature = "permits")]
+    Ed25519(&'a [u8]),
+}
+
+#[cfg(feature = "permits")]
+impl<'a> From<&'a OwnerPubkey> for Key<'a> {
+    fn from(pubkey: &'a OwnerPubkey) -> Self {
+        match pubkey {
+            OwnerPubkey::Secp256k1(key) => Key::Secp256k1(key),
+            OwnerPubkey::Ed25519(key) => Key::Ed25519(key),
+        }
+    }
+}
+
+pub fn valid_key(key: Key) -> bool {
+    match key {
+        Key::Secp256k1(key) => matches!((key.len(), key.first()), (33, Some(0x02 | 0x03)) | (65, Some(0x04))),
+        #[cfg(feature = "permits")]
+        Key::Ed25519(key) => key.len() == 32,
+    }
+}
+
+//secp256k1 signs hash as a prehashed message, ed25519 as the message itself; malformed
+//signatures are as invalid as wrong ones
+pub fn verify(api: &dyn Api, hash: &[u8], signature: &[u8], key: Key) -> bool {
+    let verified = match key {
+        Key::Secp256k1(key) => api.secp256k1_verify(hash, signature, key),
+        #[cfg(feature = "permits")]
+        Key::Ed25519(key) => api.ed25519_verify(hash, signature, key),
+    };
+    matches!(verified, Ok(true))
+}
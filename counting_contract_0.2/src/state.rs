@@ -1,12 +1,203 @@
-use cosmwasm_std::{Coin, Addr};
-use cw_storage_plus::Item;
+#[cfg(feature = "matching")]
+use cosmwasm_std::Decimal;
+#[cfg(feature = "rounds")]
+use cosmwasm_std::Decimal256;
+#[cfg(any(feature = "matching", feature = "rounds"))]
+use cosmwasm_std::Uint128;
+use cosmwasm_std::{Coin, Addr, Binary, Empty, HexBinary, Timestamp};
+use cw_storage_plus::{Item, Map, SnapshotMap, Strategy};
 use serde::{Serialize, Deserialize};
 
+#[cfg(feature = "permits")]
+use crate::msg::OwnerPubkey;
+#[cfg(feature = "export")]
+use crate::msg::SnapshotCursor;
+use crate::msg::{AuditEntry, FeeMode, Tier, TierThresholds};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CounterState {
+    pub counter: u64,
+    pub minimal_donation: Coin,
+    pub owner: Addr,
+    //donations held for the counter, sorted by denom
+    pub balance: Vec<Coin>,
+}
+
+impl CounterState {
+    pub fn deposit(&mut self, funds: &[Coin]) {
+        add_coins(&mut self.balance, funds);
+    }
+}
+
+//merges funds into coins kept sorted by denom, skipping zero amounts
+pub fn add_coins(coins: &mut Vec<Coin>, funds: &[Coin]) {
+    for coin in funds.iter().filter(|coin| !coin.amount.is_zero()) {
+        match coins.iter_mut().find(|held| held.denom == coin.denom) {
+            Some(held) => held.amount += coin.amount,
+            None => coins.push(coin.clone()),
+        }
+    }
+    coins.sort_by(|a, b| a.denom.cmp(&b.denom));
+}
+
+pub const COUNTERS: Map<&str, CounterState> = Map::new("counters");
+
+//snapshot maps don't expose the namespace of their current values
+pub const COUNTER_VALUES_NAMESPACE: &str = "counter_values";
+//counter values at past heights, written along with COUNTERS whenever a value changes
+pub const COUNTER_VALUES: SnapshotMap<&str, u64> = SnapshotMap::new(
+    COUNTER_VALUES_NAMESPACE,
+    "counter_values__checkpoints",
+    "counter_values__changelog",
+    Strategy::EveryBlock,
+);
+//first height COUNTER_VALUES knows about, the instantiation or the migration which added it
+pub const HISTORY_START: Item<u64> = Item::new("history_start");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Fee {
+    pub bps: u16,
+    pub collector: Addr,
+    pub admin: Addr,
+    pub mode: FeeMode,
+}
+
+pub const FEE: Item<Fee> = Item::new("fee");
+//sent to the collector so far, sorted by denom
+pub const FEES_COLLECTED: Item<Vec<Coin>> = Item::new("fees_collected");
+
+//seconds donations stay cancellable, missing when donations are final right away
+pub const GRACE_PERIOD: Item<u64> = Item::new("grace_period");
+
+//donation held back from the counter balance until release_at
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingDonation {
+    pub counter_id: String,
+    pub donor: Addr,
+    pub funds: Vec<Coin>,
+    //matches drawn from the sponsors' pools, given back on cancellation
+    pub matched: Vec<(Addr, Coin)>,
+    //whether it was counted
+    pub qualifying: bool,
+    pub release_at: Timestamp,
+}
+
+pub const NEXT_DONATION_ID: Item<u64> = Item::new("next_donation_id");
+pub const PENDING_DONATIONS: Map<u64, PendingDonation> = Map::new("pending_donations");
+//keyed by counter id and donation id
+pub const PENDING_BY_COUNTER: Map<(&str, u64), Empty> = Map::new("pending_by_counter");
+
+#[cfg(feature = "matching")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Sponsorship {
+    pub ratio: Decimal,
+    pub cap: Uint128,
+    //the denom is fixed by the first deposit
+    pub remaining: Coin,
+    pub matched: Uint128,
+}
+
+//matching pools keyed by counter id and sponsor
+#[cfg(feature = "matching")]
+pub const SPONSORSHIPS: Map<(&str, &Addr), Sponsorship> = Map::new("sponsorships");
+
+#[cfg(feature = "rounds")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Round {
+    pub admin: Addr,
+    pub denom: String,
+    pub end_time: Timestamp,
+    pub pool: Uint128,
+    pub projects: u32,
+    pub finalized: bool,
+}
+
+#[cfg(feature = "rounds")]
+pub const ROUNDS: Map<&str, Round> = Map::new("rounds");
+
+#[cfg(feature = "rounds")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Project {
+    pub owner: Addr,
+    pub contributed: Uint128,
+    pub donors: u32,
+    //sum of the square roots of every donor's total contribution
+    pub sum_sqrt: Decimal256,
+    //set when the round is finalized
+    pub matching: Uint128,
+    pub withdrawn: bool,
+}
+
+//keyed by round and project id
+#[cfg(feature = "rounds")]
+pub const PROJECTS: Map<(&str, &str), Project> = Map::new("projects");
+//total contributed by a donor to a project, keyed by round, project and donor
+#[cfg(feature = "rounds")]
+pub const CONTRIBUTIONS: Map<(&str, &str, &Addr), Uint128> = Map::new("contributions");
+
+//keyed by counter id and denom
+pub const TIER_THRESHOLDS: Map<(&str, &str), TierThresholds> = Map::new("tier_thresholds");
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Donor {
+    //cumulative, sorted by denom
+    pub donated: Vec<Coin>,
+    pub tier: Option<Tier>,
+}
+
+//keyed by counter id and donor
+pub const DONORS: Map<(&str, &Addr), Donor> = Map::new("donors");
+//donors with a tier, keyed by counter id, tier name and donor
+pub const DONORS_BY_TIER: Map<(&str, &str, &Addr), Empty> = Map::new("donors_by_tier");
+
+//secp256k1 public keys, keyed by counter id and attestor name
+pub const ATTESTORS: Map<(&str, &str), Binary> = Map::new("attestors");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Verification {
+    pub attestor: String,
+    pub identity: String,
+    pub attestation_hash: HexBinary,
+}
+
+//keyed by counter id and donor
+pub const VERIFIED_DONORS: Map<(&str, &Addr), Verification> = Map::new("verified_donors");
+
+//keys signing withdraw permits, keyed by counter id
+#[cfg(feature = "permits")]
+pub const OWNER_PUBKEYS: Map<&str, OwnerPubkey> = Map::new("owner_pubkeys");
+//permit nonces already used, keyed by counter id and nonce
+#[cfg(feature = "permits")]
+pub const PERMIT_NONCES: Map<(&str, u64), Empty> = Map::new("permit_nonces");
+
+//keyed by entry id, counting from 0
+pub const AUDIT_LOG: Map<u64, AuditEntry> = Map::new("audit_log");
+
+//snapshot import spread over several pages
+#[cfg(feature = "export")]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Import {
+    //sender of the instantiation, the only one allowed to send the remaining pages
+    pub importer: Addr,
+    //start_after of the next page
+    pub next: SnapshotCursor,
+}
+
+//present until the last page is imported
+#[cfg(feature = "export")]
+pub const IMPORT: Item<Import> = Item::new("import");
+
+//single counter layout of the first 0.2 release, only read when migrating
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct State {
     pub counter: u64,
     pub minimal_donation: Coin,
 }
 
-pub const STATE: Item<State> = Item::new("state");
-pub const OWNER: Item<Addr> = Item::new("owner");
+pub const LEGACY_STATE: Item<State> = Item::new("state");
+//shared by the 0.1 and single counter 0.2 layouts
+pub const LEGACY_OWNER: Item<Addr> = Item::new("owner");
+
+//0.1 layout, only read when migrating
+pub const LEGACY_COUNTER: Item<u64> = Item::new("counter");
+pub const LEGACY_MINIMAL_DONATION: Item<Coin> = Item::new("minimal_donation");
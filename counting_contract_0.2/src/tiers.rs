@@ -0,0 +1,154 @@
+use cosmwasm_std::{Addr, Coin, Empty, Event, StdResult, Storage};
+
+use crate::msg::TierThresholds;
+use crate::state::{add_coins, Donor, DONORS, DONORS_BY_TIER, TIER_THRESHOLDS};
+
+pub(crate) fn valid_thresholds(thresholds: &TierThresholds) -> bool {
+    !thresholds.bronze.is_zero() && thresholds.bronze < thresholds.silver && thresholds.silver < thresholds.gold
+}
+
+//adds a donation to the donor's totals and moves them to the tier it reaches,
+//returns the tier_changed event when they went up
+pub fn record_donation(
+    storage: &mut dyn Storage,
+    counter_id: &str,
+    donor: &Addr,
+    funds: &[Coin],
+) -> StdResult<Option<Event>> {
+    //empty donations would leave a donor entry without anything donated
+    if funds.iter().all(|coin| coin.amount.is_zero()) {
+        return Ok(None);
+    }
+    let mut stats = DONORS.may_load(storage, (counter_id, donor))?.unwrap_or_default();
+    add_coins(&mut stats.donated, funds);
+    update_tier(storage, counter_id, donor, stats)
+}
+
+//takes a cancelled donation back out of the donor's totals, which may lower their tier
+pub fn revoke_donation(storage: &mut dyn Storage, counter_id: &str, donor: &Addr, funds: &[Coin]) -> StdResult<()> {
+    let mut stats = DONORS.load(storage, (counter_id, donor))?;
+    for coin in funds {
+        if let Some(held) = stats.donated.iter_mut().find(|held| held.denom == coin.denom) {
+            held.amount -= coin.amount;
+        }
+    }
+    stats.donated.retain(|coin| !coin.amount.is_zero());
+    update_tier(storage, counter_id, donor, stats)?;
+    Ok(())
+}
+
+fn update_tier(storage: &mut dyn Storage, counter_id: &str, donor: &Addr, mut stats: Donor) -> StdResult<Option<Event>> {
+    let mut tier = None;
+    for coin in &stats.donated {
+        if let Some(thresholds) = TIER_THRESHOLDS.may_load(storage, (counter_id, &coin.denom))? {
+            tier = tier.max(thresholds.tier(coin.amount));
+        }
+    }
+
+    let previous = stats.tier;
+    stats.tier = tier;
+    DONORS.save(storage, (counter_id, donor), &stats)?;
+    if previous == tier {
+        return Ok(None);
+    }
+    if let Some(previous) = previous {
+        DONORS_BY_TIER.remove(storage, (counter_id, previous.as_str(), donor));
+    }
+    if let Some(tier) = tier {
+        DONORS_BY_TIER.save(storage, (counter_id, tier.as_str(), donor), &Empty {})?;
+    }
+
+    //lowered thresholds are applied silently
+    Ok(tier.filter(|tier| Some(*tier) > previous).map(|tier| {
+        Event::new("tier_changed")
+            .add_attribute("counter_id", counter_id)
+            .add_attribute("donor", donor)
+            .add_attribute("tier", tier.as_str())
+    }))
+}
+
+pub mod execute {
+    use cosmwasm_std::{DepsMut, Env, MessageInfo, Response};
+
+    use super::valid_thresholds;
+    use crate::audit;
+    use crate::contract::execute::{counter_id, load_counter};
+    use crate::error::ContractError;
+    use crate::msg::{AuditKind, TierThresholds};
+    use crate::state::TIER_THRESHOLDS;
+
+    pub fn set_tier_thresholds(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        counter_id: Option<String>,
+        denom: String,
+        thresholds: Option<TierThresholds>,
+    ) -> Result<Response, ContractError> {
+        let id = self::counter_id(counter_id);
+        let counter = load_counter(deps.storage, &id)?;
+        if info.sender != counter.owner {
+            return Err(ContractError::Unauthorized { owner: counter.owner.to_string() });
+        }
+
+        let previous = TIER_THRESHOLDS.may_load(deps.storage, (&id, &denom))?;
+        match &thresholds {
+            Some(thresholds) if !valid_thresholds(thresholds) => return Err(ContractError::InvalidTierThresholds {}),
+            Some(thresholds) => TIER_THRESHOLDS.save(deps.storage, (&id, &denom), thresholds)?,
+            None => TIER_THRESHOLDS.remove(deps.storage, (&id, &denom)),
+        }
+        let target = format!("{id}/{denom}");
+        audit::record(deps.storage, &env, Some(&info.sender), AuditKind::SetTierThresholds, Some(target), &previous, &thresholds)?;
+
+        let resp = Response::new()
+            .add_attribute("action", "set_tier_thresholds")
+            .add_attribute("counter_id", id)
+            .add_attribute("denom", denom);
+        Ok(resp)
+    }
+}
+
+pub mod query {
+    use cosmwasm_std::{Deps, Order, StdResult};
+    use cw_storage_plus::Bound;
+
+    use crate::contract::execute::counter_id;
+    use crate::contract::query::page_limit;
+    use crate::msg::{DenomThresholds, DonorTierResp, DonorsByTierResp, Tier, TierThresholdsResp};
+    use crate::state::{DONORS, DONORS_BY_TIER, TIER_THRESHOLDS};
+
+    pub fn tier_thresholds(deps: Deps, counter_id: Option<String>) -> StdResult<TierThresholdsResp> {
+        let thresholds = TIER_THRESHOLDS
+            .prefix(&self::counter_id(counter_id))
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| item.map(|(denom, thresholds)| DenomThresholds { denom, thresholds }))
+            .collect::<StdResult<_>>()?;
+        Ok(TierThresholdsResp { thresholds })
+    }
+
+    pub fn donor_tier(deps: Deps, counter_id: Option<String>, address: String) -> StdResult<DonorTierResp> {
+        let address = deps.api.addr_validate(&address)?;
+        let donor = DONORS
+            .may_load(deps.storage, (&self::counter_id(counter_id), &address))?
+            .unwrap_or_default();
+        Ok(DonorTierResp { tier: donor.tier, donated: donor.donated })
+    }
+
+    //donors stay in the tier of their last donation when thresholds change
+    pub fn donors_by_tier(
+        deps: Deps,
+        counter_id: Option<String>,
+        tier: Tier,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<DonorsByTierResp> {
+        let limit = page_limit(limit);
+        let start_after = start_after.map(|addr| deps.api.addr_validate(&addr)).transpose()?;
+        let donors = DONORS_BY_TIER
+            .prefix((&self::counter_id(counter_id), tier.as_str()))
+            .keys(deps.storage, start_after.as_ref().map(Bound::exclusive), None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<_>>()?;
+        Ok(DonorsByTierResp { donors })
+    }
+}
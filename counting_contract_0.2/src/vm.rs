@@ -0,0 +1,449 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+
+use cosmwasm_std::testing::mock_env;
+use cosmwasm_std::{
+    from_json, to_json_vec, Addr, BankMsg, Binary, BlockInfo, Coin, ContractInfo, ContractResult,
+    CosmosMsg, Env, MessageInfo, Response, TransactionInfo,
+};
+use cosmwasm_vm::testing::{mock_instance_options, MockApi, MockQuerier, MockStorage};
+use cosmwasm_vm::{
+    call_execute_raw, call_instantiate_raw, call_migrate_raw, call_query_raw, Backend, Instance,
+    InstanceOptions, Storage, VmResult,
+};
+use cosmwasm_vm_std::Order;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+//runs compiled contracts in cosmwasm-vm on top of a minimal mocked chain: a bank moving
+//funds sent with calls and bank messages, admins guarding migrations, and failed calls
+//reverted the way a node would do it
+
+//directory with prebuilt artifacts, e.g. the output of cosmwasm/optimizer
+pub const ARTIFACTS_ENV: &str = "WASM_ARTIFACTS_DIR";
+//toolchain used to build the artifacts when no directory is given, nightly by default
+pub const TOOLCHAIN_ENV: &str = "WASM_TOOLCHAIN";
+
+const GAS_LIMIT: u64 = 1_000_000_000_000_000;
+
+type VmInstance = Instance<MockApi, MockStorage, MockQuerier>;
+type Records = Vec<(Vec<u8>, Vec<u8>)>;
+
+//cosmwasm-vm only accepts mvp wasm while the std shipped for wasm32 by recent toolchains uses
+//newer features, so it is rebuilt with -Zbuild-std, which needs nightly with rust-src
+pub fn load_wasm(manifest_dir: &Path, lib_name: &str) -> Result<Vec<u8>, String> {
+    let path = match std::env::var_os(ARTIFACTS_ENV) {
+        Some(dir) => Path::new(&dir).join(format!("{lib_name}.wasm")),
+        None => {
+            let target_dir = manifest_dir.join("target").join("vm");
+            let toolchain = std::env::var(TOOLCHAIN_ENV).unwrap_or_else(|_| "nightly".to_owned());
+            let status = Command::new("cargo")
+                .arg(format!("+{toolchain}"))
+                .args(["build", "--release", "--lib", "--target", "wasm32-unknown-unknown"])
+                .args(["-Z", "build-std=std,panic_abort"])
+                .arg("--target-dir")
+                .arg(&target_dir)
+                .current_dir(manifest_dir)
+                .env(
+                    "RUSTFLAGS",
+                    "-C link-arg=-s -C link-arg=--allow-undefined -C target-cpu=mvp",
+                )
+                .env_remove("CARGO_ENCODED_RUSTFLAGS")
+                .env_remove("RUSTUP_TOOLCHAIN")
+                .status()
+                .map_err(|err| format!("cannot run cargo: {err}"))?;
+            if !status.success() {
+                return Err(format!(
+                    "building {} failed, set {ARTIFACTS_ENV} to use prebuilt artifacts",
+                    manifest_dir.display()
+                ));
+            }
+            target_dir
+                .join("wasm32-unknown-unknown")
+                .join("release")
+                .join(format!("{lib_name}.wasm"))
+        }
+    };
+    std::fs::read(&path).map_err(|err| format!("cannot read {}: {err}", path.display()))
+}
+
+#[derive(Clone, Debug)]
+pub struct Call<T = Response> {
+    pub result: Result<T, String>,
+    pub gas_used: u64,
+}
+
+impl<T> Call<T> {
+    fn failed(err: impl ToString) -> Self {
+        Call {
+            result: Err(err.to_string()),
+            gas_used: 0,
+        }
+    }
+}
+
+struct VmContract {
+    code_id: u64,
+    admin: Option<Addr>,
+    //only taken out while migrating
+    instance: Option<VmInstance>,
+}
+
+pub struct VmApp {
+    codes: Vec<Vec<u8>>,
+    contracts: BTreeMap<Addr, VmContract>,
+    balances: BTreeMap<Addr, Vec<Coin>>,
+    block: BlockInfo,
+}
+
+impl Default for VmApp {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VmApp {
+    pub fn new() -> Self {
+        Self {
+            codes: vec![],
+            contracts: BTreeMap::new(),
+            balances: BTreeMap::new(),
+            block: mock_env().block,
+        }
+    }
+
+    pub fn init_balance(&mut self, addr: &Addr, coins: Vec<Coin>) {
+        self.balances.insert(addr.clone(), normalize(coins));
+    }
+
+    pub fn balance(&self, addr: &Addr) -> Vec<Coin> {
+        self.balances.get(addr).cloned().unwrap_or_default()
+    }
+
+    //contracts validating addresses they are given only accept bech32 ones
+    pub fn addr_make(&self, input: &str) -> Addr {
+        Addr::unchecked(MockApi::default().addr_make(input))
+    }
+
+    pub fn block(&self) -> &BlockInfo {
+        &self.block
+    }
+
+    pub fn advance_block(&mut self, blocks: u64, seconds: u64) {
+        self.block.height += blocks;
+        self.block.time = self.block.time.plus_seconds(seconds);
+    }
+
+    //the code is compiled right away, so artifacts the vm rejects fail here
+    pub fn store_code(&mut self, wasm: Vec<u8>) -> Result<u64, String> {
+        load_instance(&wasm, new_backend()).map_err(|err| err.to_string())?;
+        self.codes.push(wasm);
+        Ok(self.codes.len() as u64)
+    }
+
+    pub fn instantiate(
+        &mut self,
+        code_id: u64,
+        sender: &Addr,
+        msg: &impl Serialize,
+        funds: &[Coin],
+        admin: Option<&Addr>,
+    ) -> (Addr, Call) {
+        let addr = Addr::unchecked(format!("contract{}", self.contracts.len()));
+        let instance = match self.code(code_id).and_then(|code| {
+            load_instance(code, new_backend()).map_err(|err| err.to_string())
+        }) {
+            Ok(instance) => instance,
+            Err(err) => return (addr, Call::failed(err)),
+        };
+        self.contracts.insert(
+            addr.clone(),
+            VmContract {
+                code_id,
+                admin: admin.cloned(),
+                instance: Some(instance),
+            },
+        );
+
+        let call = match to_json_vec(msg) {
+            Ok(msg) => self.call(&addr, sender, funds, |instance, env, info| {
+                call_instantiate_raw(instance, env, info, &msg)
+            }),
+            Err(err) => Call::failed(err),
+        };
+        if call.result.is_err() {
+            self.contracts.remove(&addr);
+        }
+        (addr, call)
+    }
+
+    pub fn execute(
+        &mut self,
+        contract: &Addr,
+        sender: &Addr,
+        msg: &impl Serialize,
+        funds: &[Coin],
+    ) -> Call {
+        match to_json_vec(msg) {
+            Ok(msg) => self.call(contract, sender, funds, |instance, env, info| {
+                call_execute_raw(instance, env, info, &msg)
+            }),
+            Err(err) => Call::failed(err),
+        }
+    }
+
+    pub fn query(&mut self, contract: &Addr, msg: &impl Serialize) -> Call<Binary> {
+        let env = to_json_vec(&self.env(contract)).expect("env is serializable");
+        let msg = match to_json_vec(msg) {
+            Ok(msg) => msg,
+            Err(err) => return Call::failed(err),
+        };
+        let Some(instance) = self.instance(contract) else {
+            return Call::failed(format!("no contract at {contract}"));
+        };
+        let (result, gas_used) =
+            measure(instance, |instance| call_query_raw(instance, &env, &msg));
+        Call { result, gas_used }
+    }
+
+    pub fn query_json<T: DeserializeOwned>(
+        &mut self,
+        contract: &Addr,
+        msg: &impl Serialize,
+    ) -> Result<T, String> {
+        let data = self.query(contract, msg).result?;
+        from_json(&data).map_err(|err| err.to_string())
+    }
+
+    //swaps the code under the contract storage, restoring the old code if migration fails
+    pub fn migrate(
+        &mut self,
+        contract: &Addr,
+        sender: &Addr,
+        code_id: u64,
+        msg: &impl Serialize,
+    ) -> Call {
+        let Some(deployed) = self.contracts.get_mut(contract) else {
+            return Call::failed(format!("no contract at {contract}"));
+        };
+        if deployed.admin.as_ref() != Some(sender) {
+            return Call::failed("Only admin can migrate");
+        }
+        let msg = match to_json_vec(msg) {
+            Ok(msg) => msg,
+            Err(err) => return Call::failed(err),
+        };
+        let code = match self.code(code_id) {
+            Ok(code) => code.to_vec(),
+            Err(err) => return Call::failed(err),
+        };
+        let deployed = self.contracts.get_mut(contract).unwrap();
+        let old_code_id = std::mem::replace(&mut deployed.code_id, code_id);
+        if let Err(err) = self.swap_code(contract, &code) {
+            return Call::failed(err);
+        }
+
+        let call = self.call(contract, sender, &[], |instance, env, _info| {
+            call_migrate_raw(instance, env, &msg)
+        });
+        if call.result.is_err() {
+            let old_code = self.codes[old_code_id as usize - 1].clone();
+            self.swap_code(contract, &old_code)
+                .expect("previous code loaded before");
+            self.contracts.get_mut(contract).unwrap().code_id = old_code_id;
+        }
+        call
+    }
+
+    fn swap_code(&mut self, contract: &Addr, code: &[u8]) -> Result<(), String> {
+        let deployed = self.contracts.get_mut(contract).unwrap();
+        let backend = deployed
+            .instance
+            .take()
+            .and_then(Instance::recycle)
+            .ok_or("contract backend lost")?;
+        let instance = load_instance(code, backend).map_err(|err| err.to_string())?;
+        deployed.instance = Some(instance);
+        Ok(())
+    }
+
+    fn code(&self, code_id: u64) -> Result<&[u8], String> {
+        (code_id as usize)
+            .checked_sub(1)
+            .and_then(|idx| self.codes.get(idx))
+            .map(Vec::as_slice)
+            .ok_or_else(|| format!("no code with id {code_id}"))
+    }
+
+    fn instance(&mut self, contract: &Addr) -> Option<&mut VmInstance> {
+        self.contracts.get_mut(contract)?.instance.as_mut()
+    }
+
+    fn env(&self, contract: &Addr) -> Env {
+        Env {
+            block: self.block.clone(),
+            transaction: Some(TransactionInfo { index: 0 }),
+            contract: ContractInfo {
+                address: contract.clone(),
+            },
+        }
+    }
+
+    //runs an entry point with funds moved to the contract first, applies the bank messages
+    //of the response and reverts the bank and contract storage when anything fails
+    fn call(
+        &mut self,
+        contract: &Addr,
+        sender: &Addr,
+        funds: &[Coin],
+        f: impl FnOnce(&mut VmInstance, &[u8], &[u8]) -> VmResult<Vec<u8>>,
+    ) -> Call {
+        let balances = self.balances.clone();
+        if let Err(err) = self.transfer(sender, contract, funds) {
+            return Call::failed(err);
+        }
+
+        let env = to_json_vec(&self.env(contract)).expect("env is serializable");
+        let info = to_json_vec(&MessageInfo {
+            sender: sender.clone(),
+            funds: funds.to_vec(),
+        })
+        .expect("info is serializable");
+        let bank = self.balances.clone();
+        let Some(instance) = self.instance(contract) else {
+            self.balances = balances;
+            return Call::failed(format!("no contract at {contract}"));
+        };
+        sync_balances(instance, &bank);
+        let storage = dump_storage(instance);
+
+        let (result, gas_used) = measure(instance, |instance| f(instance, &env, &info));
+        let result = result.and_then(|resp: Response| {
+            for msg in &resp.messages {
+                match &msg.msg {
+                    CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                        self.transfer(contract, &Addr::unchecked(to_address), amount)?
+                    }
+                    msg => return Err(format!("unsupported message {msg:?}")),
+                }
+            }
+            Ok(resp)
+        });
+
+        if result.is_err() {
+            self.balances = balances;
+            restore_storage(self.instance(contract).unwrap(), storage);
+        }
+        Call { result, gas_used }
+    }
+
+    fn transfer(&mut self, from: &Addr, to: &Addr, coins: &[Coin]) -> Result<(), String> {
+        let mut sender = self.balance(from);
+        for coin in coins.iter().filter(|coin| !coin.amount.is_zero()) {
+            let held = sender
+                .iter_mut()
+                .find(|held| held.denom == coin.denom && held.amount >= coin.amount)
+                .ok_or_else(|| format!("{from} has insufficient funds to send {coin}"))?;
+            held.amount -= coin.amount;
+        }
+        let mut recipient = self.balance(to);
+        recipient.extend(coins.iter().cloned());
+        self.balances.insert(from.clone(), normalize(sender));
+        self.balances.insert(to.clone(), normalize(recipient));
+        Ok(())
+    }
+}
+
+fn new_backend() -> Backend<MockApi, MockStorage, MockQuerier> {
+    Backend {
+        api: MockApi::default(),
+        storage: MockStorage::default(),
+        querier: MockQuerier::new(&[]),
+    }
+}
+
+fn load_instance(
+    code: &[u8],
+    backend: Backend<MockApi, MockStorage, MockQuerier>,
+) -> VmResult<VmInstance> {
+    let (_, memory_limit) = mock_instance_options();
+    let options = InstanceOptions {
+        gas_limit: GAS_LIMIT,
+    };
+    Instance::from_code(code, backend, options, memory_limit)
+}
+
+//gas is taken from the instance report, which accumulates over all calls made on it
+fn measure<T: DeserializeOwned>(
+    instance: &mut VmInstance,
+    f: impl FnOnce(&mut VmInstance) -> VmResult<Vec<u8>>,
+) -> (Result<T, String>, u64) {
+    let used = |instance: &mut VmInstance| {
+        let report = instance.create_gas_report();
+        report.used_internally + report.used_externally
+    };
+    let before = used(instance);
+    let result = f(instance)
+        .map_err(|err| err.to_string())
+        .and_then(|data| from_json::<ContractResult<T>>(&data).map_err(|err| err.to_string()))
+        .and_then(ContractResult::into_result);
+    (result, used(instance) - before)
+}
+
+fn normalize(coins: Vec<Coin>) -> Vec<Coin> {
+    let mut merged: BTreeMap<String, Coin> = BTreeMap::new();
+    for coin in coins {
+        merged
+            .entry(coin.denom.clone())
+            .and_modify(|held| held.amount += coin.amount)
+            .or_insert(coin);
+    }
+    merged
+        .into_values()
+        .filter(|coin| !coin.amount.is_zero())
+        .collect()
+}
+
+fn sync_balances(instance: &mut VmInstance, balances: &BTreeMap<Addr, Vec<Coin>>) {
+    instance
+        .with_querier(|querier| {
+            for (addr, coins) in balances {
+                let coins = coins
+                    .iter()
+                    .map(|coin| cosmwasm_vm_std::Coin::new(coin.amount.u128(), &coin.denom))
+                    .collect();
+                querier.update_balance(addr.as_str(), coins);
+            }
+            Ok(())
+        })
+        .expect("mock querier is available")
+}
+
+fn dump_storage(instance: &mut VmInstance) -> Records {
+    instance
+        .with_storage(|storage| {
+            let iterator = storage.scan(None, None, Order::Ascending).0?;
+            let mut records = vec![];
+            while let Some(record) = storage.next(iterator).0? {
+                records.push(record);
+            }
+            Ok(records)
+        })
+        .expect("mock storage is available")
+}
+
+fn restore_storage(instance: &mut VmInstance, records: Records) {
+    let current = dump_storage(instance);
+    instance
+        .with_storage(|storage| {
+            for (key, _) in current {
+                storage.remove(&key).0?;
+            }
+            for (key, value) in records {
+                storage.set(&key, &value).0?;
+            }
+            Ok(())
+        })
+        .expect("mock storage is available")
+}
@@ -0,0 +1,24 @@
+use cosmwasm_std::{to_json_vec, HexBinary, StdResult};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    contract: &'a str,
+    counter_id: &'a str,
+    donor: &'a str,
+    identity: &'a str,
+}
+
+//what an attestor signs to vouch for donor, sha256 of the json of the fields; naming the
+//contract and counter keeps the signature from being replayed elsewhere
+pub fn digest(contract: &str, counter_id: &str, donor: &str, identity: &str) -> StdResult<HexBinary> {
+    let payload = Payload {
+        contract,
+        counter_id,
+        donor,
+        identity,
+    };
+    let hash = Sha256::digest(to_json_vec(&payload)?);
+    Ok(HexBinary::from(&hash[..]))
+}
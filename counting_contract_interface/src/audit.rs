@@ -0,0 +1,38 @@
+use cosmwasm_std::{to_json_vec, HexBinary, StdResult};
+use sha2::{Digest, Sha256};
+
+use crate::msg::AuditEntry;
+
+//sha256 of the previous entry's digest, empty for the first entry, followed by the json of
+//the entry with an empty digest
+pub fn digest(entry: &AuditEntry, previous: &[u8]) -> StdResult<HexBinary> {
+    let unsigned = AuditEntry {
+        digest: HexBinary::default(),
+        ..entry.clone()
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(previous);
+    hasher.update(to_json_vec(&unsigned)?);
+    Ok(HexBinary::from(&hasher.finalize()[..]))
+}
+
+//checks a consecutive run of entries, like a page of the AuditLog query, against their
+//digests; previous is the digest of the entry before the first one, none when the run
+//starts the log. returns the id of the first entry which doesn't fit the chain
+pub fn verify(entries: &[AuditEntry], previous: Option<&HexBinary>) -> Result<(), u64> {
+    let mut previous = previous.cloned();
+    let mut expected_id = None;
+    for entry in entries {
+        let id_fits = match expected_id {
+            Some(id) => entry.id == id,
+            None => previous.is_some() || entry.id == 0,
+        };
+        let chained = previous.as_ref().map(HexBinary::as_slice).unwrap_or_default();
+        if !id_fits || digest(entry, chained).ok().as_ref() != Some(&entry.digest) {
+            return Err(entry.id);
+        }
+        previous = Some(entry.digest.clone());
+        expected_id = Some(entry.id + 1);
+    }
+    Ok(())
+}
@@ -0,0 +1,148 @@
+#[cfg(feature = "permits")]
+use cosmwasm_std::Coin;
+#[cfg(feature = "matching")]
+use cosmwasm_std::Decimal;
+use cosmwasm_std::StdError;
+use cw_utils::PaymentError;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
+    #[error("Unauthorized - only {owner} can call it")]
+    Unauthorized { owner: String },
+
+    #[error("Counter {id} not found")]
+    CounterNotFound { id: String },
+
+    #[error("Counter {id} already exists")]
+    CounterExists { id: String },
+
+    #[error("Invalid id {id:?} - use up to 64 ascii letters, digits, '-' or '_'")]
+    InvalidId { id: String },
+
+    #[cfg(feature = "matching")]
+    #[error("Matching ratio must be positive and at most {max}")]
+    InvalidMatchingRatio { max: Decimal },
+
+    #[cfg(feature = "matching")]
+    #[error("Counter can have at most {max} active sponsors")]
+    TooManySponsors { max: u32 },
+
+    #[cfg(feature = "matching")]
+    #[error("Sponsor already matches in {denom} on this counter")]
+    MatchingDenomMismatch { denom: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Round {id} not found")]
+    RoundNotFound { id: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Round {id} already exists")]
+    RoundExists { id: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Round end time must be in the future")]
+    InvalidRoundEnd {},
+
+    #[cfg(feature = "rounds")]
+    #[error("Invalid denom {denom:?} - use 1 to 128 characters")]
+    InvalidDenom { denom: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Round {id} has ended")]
+    RoundEnded { id: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Round {id} has not ended yet")]
+    RoundNotEnded { id: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Round {id} is already finalized")]
+    RoundFinalized { id: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Round {id} is not finalized yet")]
+    RoundNotFinalized { id: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Round can have at most {max} projects")]
+    TooManyProjects { max: u32 },
+
+    #[cfg(feature = "rounds")]
+    #[error("Project {project_id} not found in round {round_id}")]
+    ProjectNotFound { round_id: String, project_id: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Project {project_id} already registered in round {round_id}")]
+    ProjectExists { round_id: String, project_id: String },
+
+    #[cfg(feature = "rounds")]
+    #[error("Project {project_id} of round {round_id} has nothing to withdraw")]
+    NothingToWithdraw { round_id: String, project_id: String },
+
+    #[error("Tier thresholds must be positive and increase from bronze to gold")]
+    InvalidTierThresholds {},
+
+    #[error("Fee of {bps} bps is over 100%")]
+    InvalidFeeBps { bps: u16 },
+
+    #[error("No fee configured")]
+    FeeNotConfigured {},
+
+    #[error("Attestor public key must be a 33 or 65 byte secp256k1 key")]
+    InvalidPubkey {},
+
+    #[error("Attestor {name} not found")]
+    AttestorNotFound { name: String },
+
+    #[error("Attestation signature doesn't match")]
+    InvalidAttestation {},
+
+    #[cfg(feature = "permits")]
+    #[error("Owner public key must be a 33 or 65 byte secp256k1 or a 32 byte ed25519 key")]
+    InvalidOwnerPubkey {},
+
+    #[cfg(feature = "permits")]
+    #[error("No owner public key set for counter {id}")]
+    OwnerPubkeyNotSet { id: String },
+
+    #[cfg(feature = "permits")]
+    #[error("Permit signature doesn't match")]
+    InvalidPermit {},
+
+    #[cfg(feature = "permits")]
+    #[error("Permit {nonce} has expired")]
+    PermitExpired { nonce: u64 },
+
+    #[cfg(feature = "permits")]
+    #[error("Permit {nonce} was already used")]
+    PermitUsed { nonce: u64 },
+
+    #[cfg(feature = "permits")]
+    #[error("Counter balance is below {amount}")]
+    InsufficientBalance { amount: Coin },
+
+    #[error("Donation {id} not found or already final")]
+    DonationNotFound { id: u64 },
+
+    #[error("Grace period of donation {id} has passed")]
+    GracePeriodEnded { id: u64 },
+
+    #[cfg(feature = "export")]
+    #[error("Invalid snapshot - {reason}")]
+    InvalidSnapshot { reason: String },
+
+    #[cfg(feature = "export")]
+    #[error("Contract is importing a snapshot, it takes nothing but the remaining pages")]
+    ImportInProgress {},
+
+    #[cfg(feature = "export")]
+    #[error("No snapshot import in progress")]
+    NoImportInProgress {},
+}
@@ -0,0 +1,103 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_json_binary, Addr, Coin, CosmosMsg, StdResult, SubMsg, WasmMsg};
+use serde::Serialize;
+
+use crate::msg::{ExecuteMsg, InstantiateMsg};
+
+//address of a counting contract, builds the messages other contracts send to it
+#[cw_serde]
+pub struct CountingContractAddr(pub Addr);
+
+impl CountingContractAddr {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn execute_msg(&self, msg: &ExecuteMsg, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.0.to_string(),
+            msg: to_json_binary(msg)?,
+            funds,
+        }
+        .into())
+    }
+
+    pub fn donate_msg(&self, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        self.execute_msg(&ExecuteMsg::Donate { counter_id: None }, funds)
+    }
+
+    pub fn withdraw_msg(&self) -> StdResult<CosmosMsg> {
+        self.execute_msg(&ExecuteMsg::Withdraw { counter_id: None }, vec![])
+    }
+
+    pub fn create_counter_msg(
+        &self,
+        id: impl Into<String>,
+        minimal_donation: Coin,
+        owner: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::CreateCounter {
+            id: id.into(),
+            minimal_donation,
+            owner,
+        };
+        self.execute_msg(&msg, vec![])
+    }
+
+    pub fn donate_to_msg(&self, counter_id: impl Into<String>, funds: Vec<Coin>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Donate {
+            counter_id: Some(counter_id.into()),
+        };
+        self.execute_msg(&msg, funds)
+    }
+
+    pub fn withdraw_from_msg(&self, counter_id: impl Into<String>) -> StdResult<CosmosMsg> {
+        let msg = ExecuteMsg::Withdraw {
+            counter_id: Some(counter_id.into()),
+        };
+        self.execute_msg(&msg, vec![])
+    }
+
+    pub fn migrate_msg(&self, code_id: u64, msg: &impl Serialize) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Migrate {
+            contract_addr: self.0.to_string(),
+            new_code_id: code_id,
+            msg: to_json_binary(msg)?,
+        }
+        .into())
+    }
+
+    pub fn instantiate_msg(
+        code_id: u64,
+        msg: &InstantiateMsg,
+        label: impl Into<String>,
+        admin: Option<String>,
+    ) -> StdResult<CosmosMsg> {
+        Ok(WasmMsg::Instantiate {
+            admin,
+            code_id,
+            msg: to_json_binary(msg)?,
+            funds: vec![],
+            label: label.into(),
+        }
+        .into())
+    }
+
+    //instantiate wrapped in a submessage so the caller gets the new address in its reply
+    pub fn instantiate_submsg(
+        reply_id: u64,
+        code_id: u64,
+        msg: &InstantiateMsg,
+        label: impl Into<String>,
+        admin: Option<String>,
+    ) -> StdResult<SubMsg> {
+        let msg = Self::instantiate_msg(code_id, msg, label, admin)?;
+        Ok(SubMsg::reply_on_success(msg, reply_id))
+    }
+}
+
+impl From<Addr> for CountingContractAddr {
+    fn from(addr: Addr) -> Self {
+        Self(addr)
+    }
+}
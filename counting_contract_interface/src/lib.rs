@@ -0,0 +1,13 @@
+//messages, errors and query helpers of the counting contract without its entry points,
+//so other contracts can depend on it cheaply
+pub mod attestation;
+pub mod audit;
+pub mod error;
+pub mod helpers;
+pub mod msg;
+#[cfg(feature = "permits")]
+pub mod permit;
+pub mod querier;
+
+pub use helpers::CountingContractAddr;
+pub use querier::CountingQuerier;
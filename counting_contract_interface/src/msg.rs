@@ -0,0 +1,676 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+#[cfg(feature = "matching")]
+use cosmwasm_std::Decimal;
+use cosmwasm_std::{Addr, Binary, Coin, HexBinary, Timestamp, Uint128};
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    #[serde(default)]
+    pub counter: u64,
+    pub minimal_donation: Coin,
+    //owner of the default counter, defaults to the sender so contracts instantiating
+    //counters on behalf of someone else can hand them over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    //seconds after a donation during which the donor can cancel it and the counter owner
+    //can't withdraw it, donations are final right away when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_period: Option<u64>,
+    //platform fee taken from everything donated to counters, none when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeConfig>,
+    //seeds the storage from a snapshot exported by another instance, its owner wins
+    #[cfg(feature = "export")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<StateSnapshot>,
+}
+
+impl InstantiateMsg {
+    pub fn new(minimal_donation: Coin) -> Self {
+        Self {
+            counter: 0,
+            minimal_donation,
+            owner: None,
+            grace_period: None,
+            fee: None,
+            #[cfg(feature = "export")]
+            snapshot: None,
+        }
+    }
+}
+
+//an empty object migrates without touching the configuration, same as before it had fields
+#[cw_serde]
+#[derive(Default)]
+pub struct MigrateMsg {
+    //replaces the fee configuration, the only way to set one on instances created without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeConfig>,
+}
+
+//counter created at instantiation, used whenever a message names no counter
+pub const DEFAULT_COUNTER: &str = "default";
+
+//counter_id is left out of the json when empty, so messages for the default counter
+//look the same as before named counters existed
+#[cw_serde]
+pub enum ExecuteMsg {
+    //owner defaults to the sender
+    CreateCounter {
+        id: String,
+        minimal_donation: Coin,
+        owner: Option<String>,
+    },
+    Donate {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    //donates on behalf of beneficiary, who is credited as the donor and can cancel it
+    DonateFor {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        beneficiary: String,
+    },
+    //donates like Donate and marks the sender as verified by the attestor
+    DonateAttested {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        attestation: Attestation,
+    },
+    //refunds a donation still in its grace period to the donor, id comes from the
+    //donation_id attribute of the donate response
+    CancelDonation {
+        id: u64,
+    },
+    //sends everything donated to the counter to its owner, except donations still in
+    //their grace period
+    Withdraw {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    //deposits the single coin sent into the matching pool of the counter; every qualifying
+    //donation is then matched with ratio times the donated amount of that denom, at most cap
+    //per donation, until the sponsor's deposit runs out. funding again tops up the deposit
+    //and replaces ratio and cap
+    #[cfg(feature = "matching")]
+    FundMatching {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        ratio: Decimal,
+        cap: Uint128,
+    },
+    //counter owner only, refunds what is left of every sponsor's deposit
+    #[cfg(feature = "matching")]
+    CloseMatching {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    //owner of the default counter only, who becomes the admin of a quadratic funding round
+    //accepting donations in denom until end_time; every project registered in it adds an
+    //audit log entry, so rounds can't be opened by just anyone
+    #[cfg(feature = "rounds")]
+    CreateRound {
+        id: String,
+        denom: String,
+        end_time: Timestamp,
+    },
+    //round admin only, owner receives the contributions and the matching share
+    #[cfg(feature = "rounds")]
+    RegisterProject {
+        round_id: String,
+        project_id: String,
+        owner: String,
+    },
+    //adds the funds sent to the matching pool of the round
+    #[cfg(feature = "rounds")]
+    FundRound {
+        round_id: String,
+    },
+    #[cfg(feature = "rounds")]
+    DonateToProject {
+        round_id: String,
+        project_id: String,
+    },
+    //anyone may call it once the round ended, splits the matching pool between projects
+    #[cfg(feature = "rounds")]
+    FinalizeRound {
+        round_id: String,
+    },
+    //project owner only, sends the contributions and matching share of a finalized round
+    #[cfg(feature = "rounds")]
+    WithdrawProject {
+        round_id: String,
+        project_id: String,
+    },
+    //counter owner only, thresholds of cumulative donations in denom, none removes them;
+    //donor tiers follow the new thresholds on their next donation
+    SetTierThresholds {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        denom: String,
+        thresholds: Option<TierThresholds>,
+    },
+    //counter owner only, trusts attestations signed with the secp256k1 pubkey, compressed
+    //or not, under name; none removes the attestor, donors it verified stay verified
+    SetAttestor {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        name: String,
+        pubkey: Option<Binary>,
+    },
+    //fee admin only, replaces the whole fee configuration
+    UpdateFee {
+        fee: FeeConfig,
+    },
+    //counter owner only, key whose signatures authorize withdraw permits, none disables them
+    #[cfg(feature = "permits")]
+    SetOwnerPubkey {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        pubkey: Option<OwnerPubkey>,
+    },
+    //anyone can relay a permit signed with the owner pubkey of its counter; each nonce is
+    //accepted once per counter
+    #[cfg(feature = "permits")]
+    WithdrawWithPermit {
+        permit: WithdrawPermit,
+        //signature of permit::digest, 64 bytes for either key type
+        signature: Binary,
+    },
+    //sender of the instantiation only, the page exported after the one imported last;
+    //the contract accepts no other message until the page without next is imported
+    #[cfg(feature = "export")]
+    ImportState {
+        //boxed, a page is far larger than any other message
+        snapshot: Box<StateSnapshot>,
+    },
+}
+
+#[cfg(feature = "permits")]
+#[cw_serde]
+pub enum OwnerPubkey {
+    //33 or 65 bytes, compressed or not
+    Secp256k1(Binary),
+    //32 bytes
+    Ed25519(Binary),
+}
+
+#[cfg(feature = "permits")]
+#[cw_serde]
+pub struct WithdrawPermit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counter_id: Option<String>,
+    pub receiver: String,
+    //taken from the counter balance, the withdraw fee applies
+    pub amount: Vec<Coin>,
+    pub nonce: u64,
+    //rejected from this time on
+    pub expires: Timestamp,
+}
+
+#[cw_serde]
+pub struct Attestation {
+    pub attestor: String,
+    //off-chain identity of the donor, in whatever form the attestor uses
+    pub identity: String,
+    //64 byte secp256k1 signature of attestation::digest
+    pub signature: Binary,
+}
+
+//when the fee is taken from what counters receive
+#[cw_serde]
+#[derive(Copy, Eq)]
+pub enum FeeMode {
+    //as donations and their matches reach the counter balance, donations in their grace
+    //period once it passes
+    Donation,
+    //from the balance sent to the counter owner
+    Withdraw,
+}
+
+#[cw_serde]
+pub struct FeeConfig {
+    //out of 10000
+    pub bps: u16,
+    pub collector: String,
+    //the only one allowed to change the fee
+    pub admin: String,
+    pub mode: FeeMode,
+}
+
+//ordered from the lowest
+#[cw_serde]
+#[derive(Copy, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl Tier {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Tier::Bronze => "bronze",
+            Tier::Silver => "silver",
+            Tier::Gold => "gold",
+        }
+    }
+}
+
+#[cw_serde]
+pub struct TierThresholds {
+    pub bronze: Uint128,
+    pub silver: Uint128,
+    pub gold: Uint128,
+}
+
+impl TierThresholds {
+    pub fn tier(&self, donated: Uint128) -> Option<Tier> {
+        [(self.gold, Tier::Gold), (self.silver, Tier::Silver), (self.bronze, Tier::Bronze)]
+            .into_iter()
+            .find(|(threshold, _)| donated >= *threshold)
+            .map(|(_, tier)| tier)
+    }
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(ValueResp)]
+    Value {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    //value once every transaction of the block at height was executed, history starts at
+    //instantiation or at the migration to the release adding this query
+    #[returns(ValueResp)]
+    ValueAtHeight {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        height: u64,
+    },
+    #[returns(ConfigResp)]
+    Config {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    #[returns(CountersResp)]
+    Counters {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[cfg(feature = "matching")]
+    #[returns(MatchingResp)]
+    Matching {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    //matching of open rounds is estimated from the current contributions
+    #[cfg(feature = "rounds")]
+    #[returns(RoundResp)]
+    Round {
+        round_id: String,
+    },
+    #[returns(TierThresholdsResp)]
+    TierThresholds {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    #[returns(DonorTierResp)]
+    DonorTier {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        address: String,
+    },
+    #[returns(DonorsByTierResp)]
+    DonorsByTier {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        tier: Tier,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(AttestorsResp)]
+    Attestors {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    #[returns(VerifiedDonorsResp)]
+    VerifiedDonors {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(FeeReportResp)]
+    FeeReport {},
+    #[cfg(feature = "permits")]
+    #[returns(OwnerPubkeyResp)]
+    OwnerPubkey {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        counter_id: Option<String>,
+    },
+    //oldest entries first
+    #[returns(AuditLogResp)]
+    AuditLog {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    //a page of at most limit entries over all lists of the snapshot, follow next for the rest
+    #[cfg(feature = "export")]
+    #[returns(StateSnapshot)]
+    ExportState {
+        start_after: Option<SnapshotCursor>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct ValueResp {
+    pub value: u64,
+}
+
+#[cw_serde]
+pub struct ConfigResp {
+    pub owner: Addr,
+    pub minimal_donation: Coin,
+}
+
+#[cw_serde]
+pub struct CounterResp {
+    pub id: String,
+    pub owner: Addr,
+    pub minimal_donation: Coin,
+    pub value: u64,
+    //donated to the counter and not withdrawn yet
+    pub balance: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct CountersResp {
+    pub counters: Vec<CounterResp>,
+}
+
+#[cfg(feature = "matching")]
+#[cw_serde]
+pub struct SponsorResp {
+    pub sponsor: Addr,
+    pub ratio: Decimal,
+    pub cap: Uint128,
+    //left to match with
+    pub remaining: Coin,
+    //moved into the counter so far
+    pub matched: Coin,
+}
+
+#[cfg(feature = "rounds")]
+#[cw_serde]
+pub struct ProjectResp {
+    pub id: String,
+    pub owner: Addr,
+    pub contributed: Uint128,
+    pub donors: u32,
+    pub matching: Uint128,
+    pub withdrawn: bool,
+}
+
+#[cfg(feature = "rounds")]
+#[cw_serde]
+pub struct RoundResp {
+    pub id: String,
+    pub admin: Addr,
+    pub end_time: Timestamp,
+    pub pool: Coin,
+    pub finalized: bool,
+    pub projects: Vec<ProjectResp>,
+}
+
+#[cw_serde]
+pub struct DenomThresholds {
+    pub denom: String,
+    pub thresholds: TierThresholds,
+}
+
+#[cw_serde]
+pub struct TierThresholdsResp {
+    pub thresholds: Vec<DenomThresholds>,
+}
+
+#[cw_serde]
+pub struct DonorTierResp {
+    pub tier: Option<Tier>,
+    //cumulative donations to the counter
+    pub donated: Vec<Coin>,
+}
+
+#[cw_serde]
+pub struct DonorsByTierResp {
+    pub donors: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct AttestorResp {
+    pub name: String,
+    pub pubkey: Binary,
+}
+
+#[cw_serde]
+pub struct AttestorsResp {
+    pub attestors: Vec<AttestorResp>,
+}
+
+#[cw_serde]
+pub struct VerifiedDonor {
+    pub address: Addr,
+    pub attestor: String,
+    pub identity: String,
+    //of the latest attestation
+    pub attestation_hash: HexBinary,
+}
+
+#[cw_serde]
+pub struct VerifiedDonorsResp {
+    pub donors: Vec<VerifiedDonor>,
+}
+
+#[cw_serde]
+pub struct FeeReportResp {
+    pub fee: Option<FeeConfig>,
+    //sent to the collector so far, per denom
+    pub collected: Vec<Coin>,
+}
+
+#[cfg(feature = "permits")]
+#[cw_serde]
+pub struct OwnerPubkeyResp {
+    pub pubkey: Option<OwnerPubkey>,
+}
+
+#[cw_serde]
+#[derive(Copy, Eq)]
+pub enum AuditKind {
+    Withdraw,
+    #[cfg(feature = "matching")]
+    CloseMatching,
+    SetTierThresholds,
+    UpdateFee,
+    SetAttestor,
+    Migrate,
+    #[cfg(feature = "permits")]
+    SetOwnerPubkey,
+    //actor is the counter owner who signed the permit
+    #[cfg(feature = "permits")]
+    WithdrawWithPermit,
+    #[cfg(feature = "rounds")]
+    RegisterProject,
+    #[cfg(feature = "rounds")]
+    FinalizeRound,
+    #[cfg(feature = "rounds")]
+    WithdrawProject,
+}
+
+//privileged action, before and after hold the json of the state it changed
+#[cw_serde]
+pub struct AuditEntry {
+    pub id: u64,
+    //unknown for migrations, which only the contract admin can run
+    pub actor: Option<Addr>,
+    pub height: u64,
+    pub time: Timestamp,
+    pub kind: AuditKind,
+    //counter the action applied to, followed by the denom for tier thresholds or the name of
+    //the attestor; for round actions the round, followed by the project
+    pub target: Option<String>,
+    pub before: String,
+    pub after: String,
+    //see audit::digest
+    pub digest: HexBinary,
+}
+
+#[cw_serde]
+pub struct AuditLogResp {
+    pub entries: Vec<AuditEntry>,
+}
+
+#[cfg(feature = "matching")]
+#[cw_serde]
+pub struct MatchingResp {
+    pub sponsors: Vec<SponsorResp>,
+    //remaining matching capacity per denom over all sponsors
+    pub remaining: Vec<Coin>,
+}
+
+//bumped whenever the snapshot layout changes
+#[cfg(feature = "export")]
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+//lists of a snapshot, in the order pages go through them
+#[cfg(feature = "export")]
+#[cw_serde]
+#[derive(Copy, Eq, PartialOrd, Ord)]
+pub enum SnapshotList {
+    Counters,
+    TierThresholds,
+    Donors,
+    Attestors,
+    VerifiedDonors,
+    #[cfg(feature = "permits")]
+    OwnerPubkeys,
+    AuditLog,
+}
+
+//last entry of a page, key is its storage key within the list
+#[cfg(feature = "export")]
+#[cw_serde]
+#[derive(Eq)]
+pub struct SnapshotCursor {
+    pub list: SnapshotList,
+    pub key: Binary,
+}
+
+#[cfg(feature = "export")]
+#[cw_serde]
+pub struct ContractInfoSnapshot {
+    pub contract: String,
+    pub version: String,
+}
+
+#[cfg(feature = "export")]
+#[cw_serde]
+pub struct CounterSnapshot {
+    pub id: String,
+    pub owner: String,
+    pub counter: u64,
+    pub minimal_donation: Coin,
+}
+
+#[cfg(feature = "export")]
+#[cw_serde]
+pub struct TierThresholdsSnapshot {
+    pub counter_id: String,
+    pub denom: String,
+    pub thresholds: TierThresholds,
+}
+
+#[cfg(feature = "export")]
+#[cw_serde]
+pub struct DonorSnapshot {
+    pub counter_id: String,
+    pub address: String,
+    pub donated: Vec<Coin>,
+    pub tier: Option<Tier>,
+}
+
+#[cfg(feature = "export")]
+#[cw_serde]
+pub struct AttestorSnapshot {
+    pub counter_id: String,
+    pub name: String,
+    pub pubkey: Binary,
+}
+
+#[cfg(feature = "export")]
+#[cw_serde]
+pub struct VerifiedDonorSnapshot {
+    pub counter_id: String,
+    pub address: String,
+    pub attestor: String,
+    pub identity: String,
+    pub attestation_hash: HexBinary,
+}
+
+#[cfg(all(feature = "export", feature = "permits"))]
+#[cw_serde]
+pub struct OwnerPubkeySnapshot {
+    pub counter_id: String,
+    pub pubkey: OwnerPubkey,
+}
+
+//contract state, addresses kept as strings as they may change prefix between chains, except in
+//the audit log whose digests cover them; owner, counter and minimal_donation belong to the
+//default counter. left out as they only mean something to the exporting contract:
+//- counter balances, pending donations, matching pools and rounds, their funds stay with it
+//- counter value history, its heights belong to the exporting chain
+//- used permit nonces, permits are signed for its address only
+//every page repeats everything but the lists, an import takes it from the first one
+#[cfg(feature = "export")]
+#[cw_serde]
+pub struct StateSnapshot {
+    pub version: u32,
+    pub contract_info: ContractInfoSnapshot,
+    pub owner: String,
+    pub counter: u64,
+    pub minimal_donation: Coin,
+    //missing from version 1 snapshots
+    #[serde(default)]
+    pub counters: Vec<CounterSnapshot>,
+    //the rest is missing from version 1 and 2 snapshots; grace_period and fee replace those of
+    //the instantiate message when set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_period: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeConfig>,
+    #[serde(default)]
+    pub fees_collected: Vec<Coin>,
+    #[serde(default)]
+    pub tier_thresholds: Vec<TierThresholdsSnapshot>,
+    #[serde(default)]
+    pub donors: Vec<DonorSnapshot>,
+    #[serde(default)]
+    pub attestors: Vec<AttestorSnapshot>,
+    #[serde(default)]
+    pub verified_donors: Vec<VerifiedDonorSnapshot>,
+    //left out when empty, so only snapshots carrying keys are rejected by builds without permits
+    #[cfg(feature = "permits")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owner_pubkeys: Vec<OwnerPubkeySnapshot>,
+    //imported only when its hash chain verifies
+    #[serde(default)]
+    pub audit_log: Vec<AuditEntry>,
+    //none on the first page; snapshots before version 4 are a single page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_after: Option<SnapshotCursor>,
+    //none on the last page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<SnapshotCursor>,
+}
@@ -0,0 +1,18 @@
+use cosmwasm_std::{to_json_vec, HexBinary, StdResult};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::msg::WithdrawPermit;
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    contract: &'a str,
+    permit: &'a WithdrawPermit,
+}
+
+//what the counter owner signs to authorize permit, sha256 of the json of the contract address
+//and the permit; ed25519 keys sign these 32 bytes as the message
+pub fn digest(contract: &str, permit: &WithdrawPermit) -> StdResult<HexBinary> {
+    let hash = Sha256::digest(to_json_vec(&Payload { contract, permit })?);
+    Ok(HexBinary::from(&hash[..]))
+}
@@ -0,0 +1,75 @@
+use cosmwasm_std::{CustomQuery, QuerierWrapper, StdResult};
+
+#[cfg(feature = "export")]
+use crate::msg::{SnapshotCursor, StateSnapshot};
+#[cfg(feature = "matching")]
+use crate::msg::MatchingResp;
+use crate::msg::{ConfigResp, CountersResp, QueryMsg, ValueResp};
+
+//typed queries of a counting contract, available on any QuerierWrapper
+pub trait CountingQuerier {
+    fn value(&self, addr: impl Into<String>) -> StdResult<ValueResp>;
+
+    fn config(&self, addr: impl Into<String>) -> StdResult<ConfigResp>;
+
+    fn counter_value(&self, addr: impl Into<String>, counter_id: impl Into<String>) -> StdResult<ValueResp>;
+
+    fn counters(
+        &self,
+        addr: impl Into<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<CountersResp>;
+
+    #[cfg(feature = "matching")]
+    fn matching(&self, addr: impl Into<String>, counter_id: Option<String>) -> StdResult<MatchingResp>;
+
+    #[cfg(feature = "export")]
+    fn export_state(
+        &self,
+        addr: impl Into<String>,
+        start_after: Option<SnapshotCursor>,
+        limit: Option<u32>,
+    ) -> StdResult<StateSnapshot>;
+}
+
+impl<'a, C: CustomQuery> CountingQuerier for QuerierWrapper<'a, C> {
+    fn value(&self, addr: impl Into<String>) -> StdResult<ValueResp> {
+        self.query_wasm_smart(addr, &QueryMsg::Value { counter_id: None })
+    }
+
+    fn config(&self, addr: impl Into<String>) -> StdResult<ConfigResp> {
+        self.query_wasm_smart(addr, &QueryMsg::Config { counter_id: None })
+    }
+
+    fn counter_value(&self, addr: impl Into<String>, counter_id: impl Into<String>) -> StdResult<ValueResp> {
+        let msg = QueryMsg::Value {
+            counter_id: Some(counter_id.into()),
+        };
+        self.query_wasm_smart(addr, &msg)
+    }
+
+    fn counters(
+        &self,
+        addr: impl Into<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<CountersResp> {
+        self.query_wasm_smart(addr, &QueryMsg::Counters { start_after, limit })
+    }
+
+    #[cfg(feature = "matching")]
+    fn matching(&self, addr: impl Into<String>, counter_id: Option<String>) -> StdResult<MatchingResp> {
+        self.query_wasm_smart(addr, &QueryMsg::Matching { counter_id })
+    }
+
+    #[cfg(feature = "export")]
+    fn export_state(
+        &self,
+        addr: impl Into<String>,
+        start_after: Option<SnapshotCursor>,
+        limit: Option<u32>,
+    ) -> StdResult<StateSnapshot> {
+        self.query_wasm_smart(addr, &QueryMsg::ExportState { start_after, limit })
+    }
+}
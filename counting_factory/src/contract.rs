@@ -0,0 +1,210 @@
+use cosmwasm_std::{DepsMut, MessageInfo, Response, StdResult};
+use cw2::set_contract_version;
+
+use crate::msg::InstantiateMsg;
+use crate::state::{Config, CONFIG};
+
+const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub const CREATE_COUNTER_REPLY_ID: u64 = 1;
+
+pub fn instantiate(deps: DepsMut, info: MessageInfo, msg: InstantiateMsg) -> StdResult<Response> {
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            owner: info.sender,
+            counter_code_id: msg.counter_code_id,
+            fee: msg.fee,
+        },
+    )?;
+    Ok(Response::new())
+}
+
+pub mod execute {
+    use cosmwasm_std::{Coin, DepsMut, Env, MessageInfo, Order, Response, StdResult};
+    use counting_contract_interface::msg::{
+        FeeConfig, InstantiateMsg as CounterInstantiateMsg, MigrateMsg as CounterMigrateMsg,
+    };
+    use counting_contract_interface::CountingContractAddr;
+    use cw_storage_plus::Bound;
+
+    use super::CREATE_COUNTER_REPLY_ID;
+    use crate::error::ContractError;
+    use crate::state::{counters, PendingCounter, CONFIG, PENDING};
+
+    const DEFAULT_LIMIT: u32 = 10;
+    const MAX_LIMIT: u32 = 30;
+
+    pub fn create_counter(
+        deps: DepsMut,
+        env: Env,
+        info: MessageInfo,
+        label: String,
+        minimal_donation: Coin,
+    ) -> Result<Response, ContractError> {
+        //the factory has no way to pass funds on or withdraw them
+        cw_utils::nonpayable(&info)?;
+        let config = CONFIG.load(deps.storage)?;
+        let code_id = config.counter_code_id;
+        let msg = CounterInstantiateMsg {
+            owner: Some(info.sender.to_string()),
+            fee: config.fee,
+            ..CounterInstantiateMsg::new(minimal_donation)
+        };
+        //the factory stays admin of every counter so it can migrate them later
+        let submsg = CountingContractAddr::instantiate_submsg(
+            CREATE_COUNTER_REPLY_ID,
+            code_id,
+            &msg,
+            label.clone(),
+            Some(env.contract.address.to_string()),
+        )?;
+
+        PENDING.save(
+            deps.storage,
+            &PendingCounter {
+                creator: info.sender.clone(),
+                label,
+                code_id,
+            },
+        )?;
+
+        let resp = Response::new()
+            .add_submessage(submsg)
+            .add_attribute("action", "create_counter")
+            .add_attribute("sender", info.sender);
+        Ok(resp)
+    }
+
+    pub fn migrate_counters(
+        deps: DepsMut,
+        info: MessageInfo,
+        code_id: u64,
+        fee: Option<FeeConfig>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Response, ContractError> {
+        let mut config = CONFIG.load(deps.storage)?;
+        if info.sender != config.owner {
+            return Err(ContractError::Unauthorized {
+                owner: config.owner.to_string(),
+            });
+        }
+        config.counter_code_id = code_id;
+        if fee.is_some() {
+            config.fee = fee.clone();
+        }
+        CONFIG.save(deps.storage, &config)?;
+
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_after = start_after
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?;
+        let start = start_after.as_ref().map(Bound::exclusive);
+        let page = counters()
+            .range(deps.storage, start, None, Order::Ascending)
+            .take(limit)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        let mut resp = Response::new()
+            .add_attribute("action", "migrate_counters")
+            .add_attribute("code_id", code_id.to_string());
+        if let Some((last, _)) = page.last() {
+            resp = resp.add_attribute("last", last);
+        }
+        for (addr, mut counter) in page {
+            //already migrated by an earlier, interrupted run; a fee is applied again, which is harmless
+            if counter.code_id == code_id && fee.is_none() {
+                continue;
+            }
+            let msg = CounterMigrateMsg { fee: fee.clone() };
+            resp = resp.add_message(CountingContractAddr(addr.clone()).migrate_msg(code_id, &msg)?);
+            counter.code_id = code_id;
+            counters().save(deps.storage, &addr, &counter)?;
+        }
+        Ok(resp)
+    }
+}
+
+pub mod reply {
+    use cosmwasm_std::{DepsMut, Reply, Response};
+    use cw_utils::parse_reply_instantiate_data;
+
+    use crate::error::ContractError;
+    use crate::msg::CounterInfo;
+    use crate::state::{counters, PENDING};
+
+    pub fn create_counter(deps: DepsMut, reply: Reply) -> Result<Response, ContractError> {
+        let pending = PENDING.load(deps.storage)?;
+        PENDING.remove(deps.storage);
+
+        let resp = parse_reply_instantiate_data(reply)?;
+        let addr = deps.api.addr_validate(&resp.contract_address)?;
+
+        let counter = CounterInfo {
+            addr: addr.clone(),
+            creator: pending.creator,
+            label: pending.label,
+            code_id: pending.code_id,
+        };
+        counters().save(deps.storage, &addr, &counter)?;
+
+        Ok(Response::new().add_attribute("counter", addr))
+    }
+}
+
+pub mod query {
+    use cosmwasm_std::{Deps, Order, StdResult};
+    use cw_storage_plus::Bound;
+
+    use crate::msg::{ConfigResp, ListCountersResp};
+    use crate::state::{counters, CONFIG};
+
+    const DEFAULT_LIMIT: u32 = 10;
+    const MAX_LIMIT: u32 = 30;
+
+    pub fn config(deps: Deps) -> StdResult<ConfigResp> {
+        let config = CONFIG.load(deps.storage)?;
+        Ok(ConfigResp {
+            owner: config.owner,
+            counter_code_id: config.counter_code_id,
+            fee: config.fee,
+        })
+    }
+
+    pub fn list_counters(
+        deps: Deps,
+        creator: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<ListCountersResp> {
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+        let start_after = start_after
+            .map(|addr| deps.api.addr_validate(&addr))
+            .transpose()?;
+        let start = start_after.as_ref().map(Bound::exclusive);
+
+        let counters = match creator {
+            Some(creator) => {
+                let creator = deps.api.addr_validate(&creator)?;
+                counters()
+                    .idx
+                    .creator
+                    .prefix(creator)
+                    .range(deps.storage, start, None, Order::Ascending)
+                    .take(limit)
+                    .map(|item| item.map(|(_, counter)| counter))
+                    .collect::<StdResult<_>>()?
+            }
+            None => counters()
+                .range(deps.storage, start, None, Order::Ascending)
+                .take(limit)
+                .map(|item| item.map(|(_, counter)| counter))
+                .collect::<StdResult<_>>()?,
+        };
+
+        Ok(ListCountersResp { counters })
+    }
+}
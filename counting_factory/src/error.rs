@@ -1,4 +1,5 @@
 use cosmwasm_std::StdError;
+use cw_utils::{ParseReplyError, PaymentError};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,6 +7,15 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    ParseReply(#[from] ParseReplyError),
+
+    #[error("{0}")]
+    Payment(#[from] PaymentError),
+
     #[error("Unauthorized - only {owner} can call it")]
     Unauthorized { owner: String },
-}
\ No newline at end of file
+
+    #[error("Unknown reply id {id}")]
+    UnknownReply { id: u64 },
+}
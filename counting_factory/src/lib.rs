@@ -0,0 +1,78 @@
+use cosmwasm_std::{
+    to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
+};
+
+#[cfg(not(feature = "library"))]
+use cosmwasm_std::entry_point;
+
+use contract::CREATE_COUNTER_REPLY_ID;
+use error::ContractError;
+use msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+
+mod contract;
+pub mod error;
+pub mod msg;
+mod state;
+#[cfg(any(test, feature = "tests"))]
+pub mod multitest;
+
+//hides entry point when library feature is enabled
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    contract::instantiate(deps, info, msg)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    use msg::ExecuteMsg::*;
+
+    match msg {
+        CreateCounter {
+            label,
+            minimal_donation,
+        } => contract::execute::create_counter(deps, env, info, label, minimal_donation),
+        MigrateCounters {
+            code_id,
+            fee,
+            start_after,
+            limit,
+        } => contract::execute::migrate_counters(deps, info, code_id, fee, start_after, limit),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, reply: Reply) -> Result<Response, ContractError> {
+    match reply.id {
+        CREATE_COUNTER_REPLY_ID => contract::reply::create_counter(deps, reply),
+        id => Err(ContractError::UnknownReply { id }),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    use msg::QueryMsg::*;
+
+    match msg {
+        Config {} => to_json_binary(&contract::query::config(deps)?),
+        ListCounters {
+            creator,
+            start_after,
+            limit,
+        } => to_json_binary(&contract::query::list_counters(
+            deps,
+            creator,
+            start_after,
+            limit,
+        )?),
+    }
+}
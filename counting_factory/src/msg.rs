@@ -0,0 +1,65 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Coin};
+use counting_contract_interface::msg::FeeConfig;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    //code id of the counting contract new counters are instantiated from
+    pub counter_code_id: u64,
+    //platform fee of every counter created by the factory, none when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<FeeConfig>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    //instantiates a counting contract owned by the sender, with the factory as its admin
+    CreateCounter {
+        label: String,
+        minimal_donation: Coin,
+    },
+    //migrates a page of counters to code_id, new counters are created from it afterwards;
+    //call again with start_after set to the last migrated address until none are left;
+    //fee, when set, replaces the fee of the migrated counters and of the ones created afterwards
+    MigrateCounters {
+        code_id: u64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fee: Option<FeeConfig>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    #[returns(ConfigResp)]
+    Config {},
+    //counters ordered by address, only the ones created by creator if given
+    #[returns(ListCountersResp)]
+    ListCounters {
+        creator: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct ConfigResp {
+    pub owner: Addr,
+    pub counter_code_id: u64,
+    pub fee: Option<FeeConfig>,
+}
+
+#[cw_serde]
+pub struct CounterInfo {
+    pub addr: Addr,
+    pub creator: Addr,
+    pub label: String,
+    pub code_id: u64,
+}
+
+#[cw_serde]
+pub struct ListCountersResp {
+    pub counters: Vec<CounterInfo>,
+}
@@ -0,0 +1,120 @@
+use cosmwasm_std::{Addr, Coin, StdError, StdResult};
+use cw_multi_test::{App, AppResponse, ContractWrapper, Executor};
+
+use crate::error::ContractError;
+use counting_contract_interface::msg::FeeConfig;
+
+use crate::msg::{ConfigResp, ExecuteMsg, InstantiateMsg, ListCountersResp, QueryMsg};
+use crate::{execute, instantiate, query, reply};
+
+#[cfg(test)]
+mod tests;
+
+//errors raised outside of the factory, like a failing counter instantiation, are kept as generic errors
+fn into_contract_error(err: anyhow::Error) -> ContractError {
+    err.downcast().unwrap_or_else(|err| {
+        ContractError::Std(StdError::generic_err(format!("{err:#}")))
+    })
+}
+
+#[derive(Debug)]
+pub struct FactoryContract(Addr);
+
+impl FactoryContract {
+    pub fn addr(&self) -> &Addr {
+        &self.0
+    }
+
+    pub fn store_code(app: &mut App) -> u64 {
+        let contract = ContractWrapper::new(execute, instantiate, query).with_reply(reply);
+        app.store_code(Box::new(contract))
+    }
+
+    #[track_caller]
+    pub fn instantiate(
+        app: &mut App,
+        code_id: u64,
+        sender: &Addr,
+        label: &str,
+        counter_code_id: u64,
+        fee: Option<FeeConfig>,
+    ) -> StdResult<Self> {
+        app.instantiate_contract(
+            code_id,
+            sender.clone(),
+            &InstantiateMsg { counter_code_id, fee },
+            &[],
+            label,
+            None,
+        )
+        .map_err(|err| err.downcast().unwrap())
+        .map(FactoryContract)
+    }
+
+    //returns the address of the new counter
+    #[track_caller]
+    pub fn create_counter(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        label: &str,
+        minimal_donation: Coin,
+    ) -> Result<Addr, ContractError> {
+        let msg = ExecuteMsg::CreateCounter {
+            label: label.to_owned(),
+            minimal_donation,
+        };
+        let resp = app
+            .execute_contract(sender.clone(), self.0.clone(), &msg, &[])
+            .map_err(into_contract_error)?;
+
+        //multitest reports the new address in the instantiate event
+        resp.events
+            .iter()
+            .filter(|ev| ev.ty == "instantiate")
+            .flat_map(|ev| &ev.attributes)
+            .find(|attr| attr.key == "_contract_addr")
+            .map(|attr| Addr::unchecked(&attr.value))
+            .ok_or_else(|| StdError::generic_err("instantiate event without contract address").into())
+    }
+
+    #[track_caller]
+    pub fn migrate_counters(
+        &self,
+        app: &mut App,
+        sender: &Addr,
+        code_id: u64,
+        fee: Option<FeeConfig>,
+        start_after: Option<&Addr>,
+        limit: Option<u32>,
+    ) -> Result<AppResponse, ContractError> {
+        let msg = ExecuteMsg::MigrateCounters {
+            code_id,
+            fee,
+            start_after: start_after.map(Addr::to_string),
+            limit,
+        };
+        app.execute_contract(sender.clone(), self.0.clone(), &msg, &[])
+            .map_err(into_contract_error)
+    }
+
+    pub fn query_config(&self, app: &App) -> StdResult<ConfigResp> {
+        app.wrap()
+            .query_wasm_smart(self.0.clone(), &QueryMsg::Config {})
+    }
+
+    pub fn query_counters(
+        &self,
+        app: &App,
+        creator: Option<&Addr>,
+        start_after: Option<&Addr>,
+        limit: Option<u32>,
+    ) -> StdResult<ListCountersResp> {
+        let msg = QueryMsg::ListCounters {
+            creator: creator.map(Addr::to_string),
+            start_after: start_after.map(Addr::to_string),
+            limit,
+        };
+        app.wrap().query_wasm_smart(self.0.clone(), &msg)
+    }
+}
@@ -0,0 +1,165 @@
+use cosmwasm_std::{coins, Addr, Coin};
+use counting_contract::msg::{FeeConfig, FeeMode};
+use counting_contract::multitest::CountingContract;
+use cw_multi_test::{App, Executor};
+use cw_utils::PaymentError;
+
+use crate::error::ContractError;
+use crate::msg::ExecuteMsg;
+use crate::multitest::FactoryContract;
+
+fn setup() -> (App, FactoryContract, u64) {
+    let mut app = App::default();
+    let counter_code_id = CountingContract::store_code(&mut app);
+    let code_id = FactoryContract::store_code(&mut app);
+    let factory = FactoryContract::instantiate(
+        &mut app,
+        code_id,
+        &Addr::unchecked("owner"),
+        "Counting factory",
+        counter_code_id,
+        None,
+    )
+    .unwrap();
+    (app, factory, counter_code_id)
+}
+
+#[test]
+fn create_and_list_counters() {
+    let (mut app, factory, counter_code_id) = setup();
+    let alice = Addr::unchecked("alice");
+    let bob = Addr::unchecked("bob");
+
+    let first = factory.create_counter(&mut app, &alice, "first", Coin::new(10, "atom")).unwrap();
+    let second = factory.create_counter(&mut app, &bob, "second", Coin::new(10, "atom")).unwrap();
+    let third = factory.create_counter(&mut app, &alice, "third", Coin::new(10, "atom")).unwrap();
+
+    let counters = factory.query_counters(&app, None, None, None).unwrap().counters;
+    let addrs: Vec<_> = counters.iter().map(|counter| &counter.addr).collect();
+    assert_eq!(addrs, [&first, &second, &third]);
+    assert_eq!(counters[1].creator, bob);
+    assert_eq!(counters[1].label, "second");
+    assert_eq!(counters[1].code_id, counter_code_id);
+
+    let counters = factory.query_counters(&app, Some(&alice), None, Some(1)).unwrap().counters;
+    assert_eq!(counters.len(), 1);
+    assert_eq!(counters[0].addr, first);
+    let counters = factory.query_counters(&app, Some(&alice), Some(&first), None).unwrap().counters;
+    let labels: Vec<_> = counters.iter().map(|counter| counter.label.as_str()).collect();
+    assert_eq!(labels, ["third"]);
+}
+
+#[test]
+fn creator_owns_counter() {
+    let (mut app, factory, _) = setup();
+    let alice = Addr::unchecked("alice");
+    let sender = Addr::unchecked("sender");
+    app.init_modules(|router, _api, storage| {
+        router.bank.init_balance(storage, &sender, coins(10, "atom")).unwrap();
+    });
+
+    let addr = factory.create_counter(&mut app, &alice, "alice", Coin::new(10, "atom")).unwrap();
+    let counter = CountingContract::from(addr.clone());
+    assert_eq!(counter.query_config(&app).unwrap().owner, alice);
+    assert_eq!(
+        app.wrap().query_wasm_contract_info(&addr).unwrap().admin,
+        Some(factory.addr().to_string())
+    );
+
+    counter.donate(&mut app, &sender, &coins(10, "atom")).unwrap();
+    counter.withdraw(&mut app, &alice).unwrap();
+    assert_eq!(app.wrap().query_all_balances(&alice).unwrap(), coins(10, "atom"));
+}
+
+#[test]
+fn create_counter_rejects_funds() {
+    let (mut app, factory, _) = setup();
+    let alice = Addr::unchecked("alice");
+    app.init_modules(|router, _api, storage| {
+        router.bank.init_balance(storage, &alice, coins(10, "atom")).unwrap();
+    });
+
+    let msg = ExecuteMsg::CreateCounter {
+        label: "alice".to_owned(),
+        minimal_donation: Coin::new(10, "atom"),
+    };
+    let err = app
+        .execute_contract(alice.clone(), factory.addr().clone(), &msg, &coins(10, "atom"))
+        .unwrap_err();
+    assert_eq!(err.downcast::<ContractError>().unwrap(), ContractError::Payment(PaymentError::NonPayable {}));
+    assert_eq!(app.wrap().query_all_balances(&alice).unwrap(), coins(10, "atom"));
+    assert!(factory.query_counters(&app, None, None, None).unwrap().counters.is_empty());
+}
+
+#[test]
+fn migrate_counters_in_batches() {
+    let (mut app, factory, _) = setup();
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let new_code_id = CountingContract::store_code(&mut app);
+    for label in ["first", "second", "third"] {
+        factory.create_counter(&mut app, &alice, label, Coin::new(10, "atom")).unwrap();
+    }
+
+    let err = factory.migrate_counters(&mut app, &alice, new_code_id, None, None, None).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized { owner: owner.to_string() });
+
+    factory.migrate_counters(&mut app, &owner, new_code_id, None, None, Some(2)).unwrap();
+    let counters = factory.query_counters(&app, None, None, None).unwrap().counters;
+    let code_ids: Vec<_> = counters.iter().map(|counter| counter.code_id).collect();
+    assert_eq!(code_ids[..2], [new_code_id, new_code_id]);
+    assert_ne!(code_ids[2], new_code_id);
+
+    //migrating from the start again skips the counters already done
+    factory.migrate_counters(&mut app, &owner, new_code_id, None, None, None).unwrap();
+    for counter in factory.query_counters(&app, None, None, None).unwrap().counters {
+        assert_eq!(counter.code_id, new_code_id);
+        assert_eq!(app.wrap().query_wasm_contract_info(&counter.addr).unwrap().code_id, new_code_id);
+    }
+    assert_eq!(factory.query_config(&app).unwrap().counter_code_id, new_code_id);
+
+    let addr = factory.create_counter(&mut app, &alice, "fourth", Coin::new(10, "atom")).unwrap();
+    assert_eq!(app.wrap().query_wasm_contract_info(&addr).unwrap().code_id, new_code_id);
+}
+
+#[test]
+fn counters_get_the_factory_fee() {
+    let mut app = App::default();
+    let owner = Addr::unchecked("owner");
+    let alice = Addr::unchecked("alice");
+    let fee = |bps| FeeConfig {
+        bps,
+        collector: "collector".to_owned(),
+        admin: "fee_admin".to_owned(),
+        mode: FeeMode::Donation,
+    };
+    let counter_code_id = CountingContract::store_code(&mut app);
+    let code_id = FactoryContract::store_code(&mut app);
+    let factory = FactoryContract::instantiate(
+        &mut app,
+        code_id,
+        &owner,
+        "Counting factory",
+        counter_code_id,
+        Some(fee(100)),
+    )
+    .unwrap();
+
+    let first = factory.create_counter(&mut app, &alice, "first", Coin::new(10, "atom")).unwrap();
+    let first = CountingContract::from(first);
+    assert_eq!(first.query_fee_report(&app).unwrap().fee, Some(fee(100)));
+
+    //counters already on the code are migrated again to pick up the new fee
+    factory.migrate_counters(&mut app, &owner, counter_code_id, Some(fee(200)), None, None).unwrap();
+    assert_eq!(first.query_fee_report(&app).unwrap().fee, Some(fee(200)));
+    assert_eq!(factory.query_config(&app).unwrap().fee, Some(fee(200)));
+
+    let second = factory.create_counter(&mut app, &alice, "second", Coin::new(10, "atom")).unwrap();
+    let second = CountingContract::from(second);
+    assert_eq!(second.query_fee_report(&app).unwrap().fee, Some(fee(200)));
+
+    //leaving the fee out keeps the current one
+    factory.migrate_counters(&mut app, &owner, counter_code_id, None, None, None).unwrap();
+    assert_eq!(factory.query_config(&app).unwrap().fee, Some(fee(200)));
+    assert_eq!(second.query_fee_report(&app).unwrap().fee, Some(fee(200)));
+}
@@ -0,0 +1,50 @@
+use cosmwasm_std::Addr;
+use counting_contract_interface::msg::FeeConfig;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, MultiIndex};
+use serde::{Deserialize, Serialize};
+
+use crate::msg::CounterInfo;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Config {
+    pub owner: Addr,
+    pub counter_code_id: u64,
+    //checked by the counters when they are instantiated or migrated
+    #[serde(default)]
+    pub fee: Option<FeeConfig>,
+}
+
+pub const CONFIG: Item<Config> = Item::new("config");
+
+//counter being instantiated, picked up by the instantiate reply
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingCounter {
+    pub creator: Addr,
+    pub label: String,
+    pub code_id: u64,
+}
+
+pub const PENDING: Item<PendingCounter> = Item::new("pending");
+
+pub struct CounterIndexes<'a> {
+    pub creator: MultiIndex<'a, Addr, CounterInfo, &'a Addr>,
+}
+
+impl<'a> IndexList<CounterInfo> for CounterIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CounterInfo>> + '_> {
+        let v: Vec<&dyn Index<CounterInfo>> = vec![&self.creator];
+        Box::new(v.into_iter())
+    }
+}
+
+//counters keyed by address, indexed by creator
+pub fn counters<'a>() -> IndexedMap<'a, &'a Addr, CounterInfo, CounterIndexes<'a>> {
+    let indexes = CounterIndexes {
+        creator: MultiIndex::new(
+            |_pk, counter| counter.creator.clone(),
+            "counters",
+            "counters__creator",
+        ),
+    };
+    IndexedMap::new("counters", indexes)
+}